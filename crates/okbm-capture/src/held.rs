@@ -0,0 +1,63 @@
+use crate::{KeyboardEvent, scancode};
+use std::collections::{HashMap, HashSet};
+
+/// Keys held while input is captured, whether they were sent by scancode or keysym.
+#[derive(Debug, Default)]
+pub struct HeldKeys {
+    keys: HashSet<scancode::Linux>,
+    // the keysyms the keys pressed in keysym mode were sent as
+    keysyms: HashMap<scancode::Linux, u32>,
+}
+
+impl HeldKeys {
+    pub fn update(&mut self, event: &KeyboardEvent) {
+        let (key, keysym, state) = match *event {
+            KeyboardEvent::Key { key, state, .. } => (key, None, state),
+            KeyboardEvent::Keysym {
+                key, keysym, state, ..
+            } => (key, Some(keysym), state),
+            KeyboardEvent::Modifiers { .. } => return,
+        };
+        let Ok(key) = scancode::Linux::try_from(key) else {
+            return;
+        };
+        if state == 0 {
+            self.keys.remove(&key);
+            self.keysyms.remove(&key);
+        } else {
+            self.keys.insert(key);
+            if let Some(keysym) = keysym {
+                self.keysyms.insert(key, keysym);
+            }
+        }
+    }
+
+    pub fn keys(&self) -> &HashSet<scancode::Linux> {
+        &self.keys
+    }
+
+    /// Releases of the held keys, sent the way they were pressed.
+    pub fn releases(&self, time: u32) -> impl Iterator<Item = (scancode::Linux, KeyboardEvent)> {
+        self.keys.iter().map(move |&key| {
+            let release = match self.keysyms.get(&key) {
+                Some(&keysym) => KeyboardEvent::Keysym {
+                    time,
+                    key: key as u32,
+                    keysym,
+                    state: 0,
+                },
+                None => KeyboardEvent::Key {
+                    time,
+                    key: key as u32,
+                    state: 0,
+                },
+            };
+            (key, release)
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.keysyms.clear();
+    }
+}
@@ -8,26 +8,40 @@ mod wayland;
 #[cfg(all(unix, not(target_os = "macos")))]
 pub(crate) use wayland::*;
 
+mod held;
+pub use held::HeldKeys;
+
+mod switch;
+pub(crate) use switch::SwitchGate;
+pub use switch::{SwitchBlock, SwitchPolicy};
+
 use futures::{Stream, ready};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::{
-    mem::swap,
+    fmt, io,
+    path::PathBuf,
     task::{Context, Poll},
 };
 
+use eyre::Report;
+
 pub use eyre::Result;
 pub use futures::StreamExt;
 pub use okbm_common::*;
 
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum CaptureEvent {
     Begin,
 
     Input(Event),
+
+    // files dragged onto the edge and dropped there, without capturing
+    Dropped(Vec<PathBuf>),
 }
 
-#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Position {
     Left,
     Right,
@@ -46,6 +60,85 @@ impl Position {
     }
 }
 
+/// A segment of a screen edge leading to a peer, e.g. the top half of the
+/// right edge of output DP-1.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Edge {
+    pub position: Position,
+    // output of the edge, the outermost outputs on that side when unset.
+    // macOS doesn't name its displays, it only takes the outer bounds and no output.
+    pub output: Option<String>,
+    // start and end of the segment in percent of the edge's length,
+    // from its top or left end
+    pub start: u8,
+    pub end: u8,
+}
+
+impl Edge {
+    /// Whether the segment contains the point `fraction` of the way along the edge.
+    pub fn contains(&self, fraction: f64) -> bool {
+        let fraction = fraction * 100.;
+        fraction >= self.start as f64 && fraction <= self.end as f64
+    }
+}
+
+impl From<Position> for Edge {
+    // the whole edge
+    fn from(position: Position) -> Self {
+        Self {
+            position,
+            output: None,
+            start: 0,
+            end: 100,
+        }
+    }
+}
+
+/// An output of the local screen, in the compositor's global coordinates.
+/// macOS doesn't name its displays.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Screen {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// What went wrong in the backend, the stream goes on unless the connection
+/// to the compositor is lost.
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(io::Error),
+    // the compositor closed the connection, nothing follows
+    Protocol(String),
+    // an event we can't make sense of
+    Dispatch(String),
+    // the surface of an edge couldn't be set up
+    Surface(io::Error),
+    UnknownOutput(u32),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Io(e) => write!(f, "i/o error: {e}"),
+            CaptureError::Protocol(e) => write!(f, "protocol violation: {e}"),
+            CaptureError::Dispatch(e) => write!(f, "failed to dispatch event: {e}"),
+            CaptureError::Surface(e) => write!(f, "failed to create edge surface: {e}"),
+            CaptureError::UnknownOutput(name) => write!(f, "unknown output {name}"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<io::Error> for CaptureError {
+    fn from(e: io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
 pub enum CaptureKind {
     #[cfg(target_os = "macos")]
     MacOS(MacOSInputCapture),
@@ -57,7 +150,7 @@ impl CaptureKind {
     pub fn poll_next_unpin(
         &mut self,
         cx: &mut Context<'_>,
-    ) -> Poll<Option<Result<(Position, CaptureEvent)>>> {
+    ) -> Poll<Option<Result<(Edge, CaptureEvent)>>> {
         match self {
             #[cfg(target_os = "macos")]
             CaptureKind::MacOS(capture) => capture.poll_next_unpin(cx),
@@ -66,12 +159,39 @@ impl CaptureKind {
         }
     }
 
-    pub async fn create(&mut self, pos: Position) -> Result<()> {
+    pub async fn create(&mut self, edge: Edge) -> Result<()> {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.create(edge).await,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.create(edge).await,
+        }
+    }
+
+    pub async fn destroy(&mut self, edge: Edge) -> Result<()> {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.destroy(edge).await,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.destroy(edge).await,
+        }
+    }
+
+    pub async fn terminate(&mut self) -> Result<()> {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.terminate().await,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.terminate(),
+        }
+    }
+
+    pub async fn grab(&mut self, edge: Edge) -> Result<()> {
         match self {
             #[cfg(target_os = "macos")]
-            CaptureKind::MacOS(capture) => capture.create(pos).await,
+            CaptureKind::MacOS(capture) => capture.grab(edge).await,
             #[cfg(all(unix, not(target_os = "macos")))]
-            CaptureKind::Wayland(capture) => capture.create(pos).await,
+            CaptureKind::Wayland(capture) => capture.grab(edge).await,
         }
     }
 
@@ -83,16 +203,82 @@ impl CaptureKind {
             CaptureKind::Wayland(capture) => capture.release().await,
         }
     }
+
+    pub async fn set_key_mode(&mut self, mode: KeyMode) {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.set_key_mode(mode).await,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.set_key_mode(mode),
+        }
+    }
+
+    pub async fn set_switch_policy(&mut self, policy: SwitchPolicy) {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.set_switch_policy(policy).await,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.set_switch_policy(policy),
+        }
+    }
+
+    pub async fn set_switch_block(&mut self, edge: Edge, block: SwitchBlock) -> Result<()> {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.set_switch_block(edge, block).await,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.set_switch_block(edge, block),
+        }
+    }
+
+    pub fn keymap(&self) -> Option<Keymap> {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.keymap(),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.keymap(),
+        }
+    }
+
+    pub fn repeat_info(&self) -> Option<RepeatInfo> {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.repeat_info(),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.repeat_info(),
+        }
+    }
+
+    pub fn locks(&self) -> Option<u32> {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.locks(),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.locks(),
+        }
+    }
+
+    pub fn screens(&self) -> Vec<Screen> {
+        match self {
+            #[cfg(target_os = "macos")]
+            CaptureKind::MacOS(capture) => capture.screens(),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            CaptureKind::Wayland(capture) => capture.screens(),
+        }
+    }
 }
 
 pub struct Capture {
     capture: CaptureKind,
 
-    pressed_keys: HashSet<scancode::Linux>,
+    pressed_keys: HeldKeys,
 
-    position_map: HashMap<Position, Vec<u32>>,
+    // the edge input is captured through, its keys go with it
+    captured: Option<Edge>,
 
-    id_map: HashMap<u32, Position>,
+    edge_map: HashMap<Edge, Vec<u32>>,
+
+    id_map: HashMap<u32, Edge>,
 
     pending: VecDeque<(u32, CaptureEvent)>,
 }
@@ -110,41 +296,162 @@ impl Capture {
                 capture
             },
             pressed_keys: Default::default(),
-            position_map: Default::default(),
+            captured: None,
+            edge_map: Default::default(),
             id_map: Default::default(),
             pending: Default::default(),
         })
     }
 
-    pub async fn create(&mut self, id: u32, pos: Position) -> Result<()> {
-        assert!(!self.id_map.contains_key(&id));
+    pub async fn create(&mut self, id: u32, edge: impl Into<Edge>) -> Result<()> {
+        let capture = &mut self.capture;
+        add_id(
+            &mut self.edge_map,
+            &mut self.id_map,
+            id,
+            edge.into(),
+            async |edge| capture.create(edge).await,
+        )
+        .await
+    }
 
-        self.id_map.insert(id, pos);
+    /// Removes `id`, the barrier of its edge goes away with the last id using it.
+    pub async fn destroy(&mut self, id: u32) -> Result<()> {
+        let Some(edge) = self.id_map.remove(&id) else {
+            return Err(Report::msg(format!("unknown capture {id}")));
+        };
+        self.pending.retain(|(pending, _)| *pending != id);
+
+        let ids = self.edge_map.entry(edge.clone()).or_default();
+        ids.retain(|&other| other != id);
+        if ids.is_empty() {
+            self.edge_map.remove(&edge);
+            if self
+                .captured
+                .take_if(|captured| *captured == edge)
+                .is_some()
+            {
+                self.pressed_keys.clear();
+            }
+            self.capture.destroy(edge).await?;
+        }
+        Ok(())
+    }
 
-        if let Some(v) = self.position_map.get_mut(&pos) {
-            v.push(id);
-            Ok(())
-        } else {
-            self.position_map.insert(pos, vec![id]);
-            self.capture.create(pos).await
+    /// Removes every barrier and gives the pointer back, the stream yields
+    /// nothing more afterwards.
+    pub async fn terminate(&mut self) -> Result<()> {
+        let edges: Vec<_> = self.edge_map.drain().map(|(edge, _)| edge).collect();
+        self.id_map.clear();
+        self.pending.clear();
+        self.pressed_keys.clear();
+        self.captured = None;
+        for edge in edges {
+            self.capture.destroy(edge).await?;
         }
+        self.capture.terminate().await
     }
 
     pub async fn release(&mut self) -> Result<()> {
         self.pressed_keys.clear();
+        self.captured = None;
         self.capture.release().await
     }
 
-    fn update_pressed_keys(&mut self, key: u32, state: u8) {
-        if let Ok(scancode) = scancode::Linux::try_from(key) {
-            println!("key: {key}, state: {state}, scancode: {scancode:?}");
+    /// Starts capturing for `id` without the pointer crossing its barrier,
+    /// whatever was captured before. On Wayland the pointer can only be captured
+    /// by crossing an edge, grabbing from the local session takes the keyboard alone.
+    pub async fn grab(&mut self, id: u32) -> Result<()> {
+        let Some(edge) = self.id_map.get(&id).cloned() else {
+            return Err(Report::msg(format!("unknown capture {id}")));
+        };
+        self.pressed_keys.clear();
+        self.capture.grab(edge.clone()).await?;
+        self.captured = Some(edge);
+        self.pending.push_back((id, CaptureEvent::Begin));
+        Ok(())
+    }
 
-            match state {
-                1 => self.pressed_keys.insert(scancode),
-                _ => self.pressed_keys.remove(&scancode),
-            };
+    /// Keys held while input is captured, keysyms included by the key typing them.
+    pub fn pressed_keys(&self) -> &HashSet<scancode::Linux> {
+        self.pressed_keys.keys()
+    }
+
+    /// Releases of the keys held while input is captured, sent the way they were pressed.
+    pub fn releases(&self, time: u32) -> impl Iterator<Item = (scancode::Linux, KeyboardEvent)> {
+        self.pressed_keys.releases(time)
+    }
+
+    /// Whether keys are captured by position or by the character they produce.
+    pub async fn set_key_mode(&mut self, mode: KeyMode) {
+        self.capture.set_key_mode(mode).await
+    }
+
+    /// When reaching an edge starts capturing.
+    pub async fn set_switch_policy(&mut self, policy: SwitchPolicy) {
+        self.capture.set_switch_policy(policy).await
+    }
+
+    /// What keeps the edge of `id` from starting to capture. It applies to the
+    /// whole edge, the last one set wins when several ids share it.
+    pub async fn set_switch_block(&mut self, id: u32, block: SwitchBlock) -> Result<()> {
+        let Some(edge) = self.id_map.get(&id).cloned() else {
+            return Err(Report::msg(format!("unknown capture {id}")));
+        };
+        self.capture.set_switch_block(edge, block).await
+    }
+
+    /// Keymap of the local keyboard, to be installed by the peers receiving our events.
+    pub fn keymap(&self) -> Option<Keymap> {
+        self.capture.keymap()
+    }
+
+    /// Key repeat of the local keyboard, if the platform tells.
+    pub fn repeat_info(&self) -> Option<RepeatInfo> {
+        self.capture.repeat_info()
+    }
+
+    /// Xkb mask of the local Caps/Num/Scroll Lock state. Unknown on Wayland until
+    /// the compositor gives the capture keyboard focus, they then follow in a
+    /// `KeyboardEvent::Modifiers`.
+    pub fn locks(&self) -> Option<u32> {
+        self.capture.locks()
+    }
+
+    /// Outputs of the local screen, as far as they are known yet.
+    pub fn screens(&self) -> Vec<Screen> {
+        self.capture.screens()
+    }
+}
+
+// registers `id` on `edge`, the barrier of a new edge is created with `create`.
+// Nothing is registered if that fails
+async fn add_id(
+    edge_map: &mut HashMap<Edge, Vec<u32>>,
+    id_map: &mut HashMap<u32, Edge>,
+    id: u32,
+    edge: Edge,
+    create: impl AsyncFnOnce(Edge) -> Result<()>,
+) -> Result<()> {
+    if id_map.contains_key(&id) {
+        return Err(Report::msg(format!("capture {id} already exists")));
+    }
+    if edge.start >= edge.end || edge.end > 100 {
+        return Err(Report::msg(format!(
+            "invalid segment [{}, {}]",
+            edge.start, edge.end
+        )));
+    }
+
+    match edge_map.get_mut(&edge) {
+        Some(ids) => ids.push(id),
+        None => {
+            create(edge.clone()).await?;
+            edge_map.insert(edge.clone(), vec![id]);
         }
     }
+    id_map.insert(id, edge);
+    Ok(())
 }
 
 impl Stream for Capture {
@@ -158,50 +465,100 @@ impl Stream for Capture {
             return Poll::Ready(Some(Ok(e)));
         }
 
-        // ready
-        let event = ready!(self.capture.poll_next_unpin(cx));
+        loop {
+            // ready
+            let event = ready!(self.capture.poll_next_unpin(cx));
 
-        // stream closed
-        let event = match event {
-            Some(e) => e,
-            None => return Poll::Ready(None),
-        };
+            // stream closed
+            let event = match event {
+                Some(e) => e,
+                None => return Poll::Ready(None),
+            };
 
-        // error occurred
-        let (pos, event) = match event {
-            Ok(e) => e,
-            Err(e) => return Poll::Ready(Some(Err(e))),
-        };
+            // error occurred
+            let (edge, event) = match event {
+                Ok(e) => e,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
 
-        // handle key presses
-        if let CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key { key, state, .. })) = event {
-            self.update_pressed_keys(key, state);
-        }
-
-        let len = self
-            .position_map
-            .get(&pos)
-            .map(|ids| ids.len())
-            .unwrap_or(0);
-
-        match len {
-            0 => Poll::Pending,
-            1 => Poll::Ready(Some(Ok((
-                self.position_map.get(&pos).expect("no id")[0],
-                event,
-            )))),
-            _ => {
-                let mut position_map = HashMap::new();
-                swap(&mut self.position_map, &mut position_map);
-                {
-                    for &id in position_map.get(&pos).expect("position") {
-                        self.pending.push_back((id, event));
-                    }
-                }
-                swap(&mut self.position_map, &mut position_map);
+            let this = &mut *self;
+            let Some((&id, rest)) = this.edge_map.get(&edge).and_then(|ids| ids.split_first())
+            else {
+                // left over from a destroyed edge, the backend may have more
+                continue;
+            };
+            for &other in rest {
+                this.pending.push_back((other, event.clone()));
+            }
 
-                Poll::Ready(Some(Ok(self.pending.pop_front().expect("event"))))
+            // handle key presses
+            match event {
+                CaptureEvent::Begin => this.captured = Some(edge),
+                CaptureEvent::Input(Event::Keyboard(ref event)) => {
+                    this.pressed_keys.update(event);
+                }
+                _ => {}
             }
+            return Poll::Ready(Some(Ok((id, event))));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn failed_barriers_leave_nothing_behind() {
+        let (mut edge_map, mut id_map) = (HashMap::new(), HashMap::new());
+        let edge = Edge::from(Position::Left);
+
+        let failing = async |_| Err(Report::msg("no compositor"));
+        let result = add_id(&mut edge_map, &mut id_map, 1, edge.clone(), failing).await;
+        assert!(result.is_err());
+        assert!(edge_map.is_empty() && id_map.is_empty());
+
+        // the id and the edge can be used again, the barrier is created this time
+        let mut created = vec![];
+        let create = async |edge| {
+            created.push(edge);
+            Ok(())
+        };
+        add_id(&mut edge_map, &mut id_map, 1, edge.clone(), create)
+            .await
+            .unwrap();
+        assert_eq!(created, [Edge::from(Position::Left)]);
+        assert_eq!(edge_map[&edge], [1]);
+    }
+
+    #[tokio::test]
+    async fn ids_share_the_barrier_of_their_edge() {
+        let (mut edge_map, mut id_map) = (HashMap::new(), HashMap::new());
+        let edge = Edge::from(Position::Left);
+
+        add_id(
+            &mut edge_map,
+            &mut id_map,
+            1,
+            edge.clone(),
+            async |_| Ok(()),
+        )
+        .await
+        .unwrap();
+        let unexpected = async |_| Err(Report::msg("the barrier exists already"));
+        add_id(&mut edge_map, &mut id_map, 2, edge.clone(), unexpected)
+            .await
+            .unwrap();
+        assert_eq!(edge_map[&edge], [1, 2]);
+
+        let again = add_id(
+            &mut edge_map,
+            &mut id_map,
+            2,
+            edge.clone(),
+            async |_| Ok(()),
+        )
+        .await;
+        assert!(again.is_err());
+    }
+}
@@ -2,8 +2,6 @@ use crate::*;
 
 use eyre::Report;
 
-use bitflags::bitflags;
-
 use core_foundation::base::{CFRelease, kCFAllocatorDefault};
 use core_foundation::date::CFTimeInterval;
 use core_foundation::number::{CFBooleanRef, kCFBooleanTrue};
@@ -12,23 +10,40 @@ use core_foundation::string::{CFStringCreateWithCString, CFStringRef, kCFStringE
 use core_graphics::base::{CGError, kCGErrorSuccess};
 use core_graphics::display::{CGDisplay, CGPoint};
 use core_graphics::event::{
-    CGEvent, CGEventFlags, CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement,
+    CGEvent, CGEventField, CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement,
     CGEventTapProxy, CGEventType, CallbackResult, EventField,
 };
 use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+use core_graphics::sys::CGEventRef;
+use foreign_types::ForeignType;
 use futures::Stream;
-use keycode::{KeyMap, KeyMapping};
-use libc::c_void;
+use libc::{c_ulong, c_void};
+use okbm_common::objc::{
+    class, ns_string, objc_autoreleasePoolPop, objc_autoreleasePoolPush, send, send1,
+};
 use std::cell::LazyCell;
-use std::collections::HashSet;
-use std::ffi::{CString, c_char};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString, c_char};
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll, ready};
 use std::thread::{self};
+use std::time::Instant;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::sync::{Mutex, oneshot};
 
+// scroll phase fields, missing from core_graphics::event::EventField
+const SCROLL_WHEEL_EVENT_SCROLL_PHASE: CGEventField = 99;
+const SCROLL_WHEEL_EVENT_MOMENTUM_PHASE: CGEventField = 123;
+
+// pixels scrolled per line, as in the point delta fields
+const PIXELS_PER_LINE: f64 = 10.;
+
+// CGScrollPhase
+const SCROLL_PHASE_ENDED: i64 = 4;
+const SCROLL_PHASE_CANCELLED: i64 = 8;
+
 #[derive(Debug, Default)]
 struct Bounds {
     xmin: f64,
@@ -39,17 +54,28 @@ struct Bounds {
 
 #[derive(Debug)]
 struct InputCaptureState {
-    active_clients: LazyCell<HashSet<Position>>,
-    current_pos: Option<Position>,
+    active_clients: LazyCell<HashSet<Edge>>,
+    current_pos: Option<Edge>,
+    gate: SwitchGate,
     bounds: Bounds,
+    key_mode: KeyMode,
+    // keysyms sent for the keys currently pressed, released as such
+    pressed_keysyms: HashMap<u32, u32>,
+    // change count of the drag pasteboard when the left button went down
+    // and the edge a drag is held against
+    drag_count: Option<isize>,
+    dragging: Option<Edge>,
 }
 
 #[derive(Debug)]
 enum ProducerEvent {
     Release,
-    Create(Position),
-    Destroy(Position),
-    Grab(Position),
+    Create(Edge),
+    Destroy(Edge),
+    Grab(Edge),
+    SetKeyMode(KeyMode),
+    SetSwitchPolicy(SwitchPolicy),
+    SetSwitchBlock(Edge, SwitchBlock),
     EventTapDisabled,
 }
 
@@ -58,26 +84,107 @@ impl InputCaptureState {
         let mut res = Self {
             active_clients: LazyCell::new(HashSet::new),
             current_pos: None,
+            gate: SwitchGate::default(),
             bounds: Bounds::default(),
+            key_mode: KeyMode::default(),
+            pressed_keysyms: HashMap::new(),
+            drag_count: None,
+            dragging: None,
         };
         res.update_bounds()?;
 
         Ok(res)
     }
 
-    fn crossed(&mut self, event: &CGEvent) -> Option<Position> {
+    // edges are segments of the outer bounds of all displays, `create` rejects outputs
+    fn crossed(&mut self, event: &CGEvent) -> Option<Edge> {
         let location = event.location();
         let relative_x = event.get_double_value_field(EventField::MOUSE_EVENT_DELTA_X);
         let relative_y = event.get_double_value_field(EventField::MOUSE_EVENT_DELTA_Y);
+        let (x, y) = (location.x + relative_x, location.y + relative_y);
 
-        for &position in self.active_clients.iter() {
-            if (position == Position::Left && (location.x + relative_x) <= self.bounds.xmin)
-                || (position == Position::Right && (location.x + relative_x) >= self.bounds.xmax)
-                || (position == Position::Top && (location.y + relative_y) <= self.bounds.ymin)
-                || (position == Position::Bottom && (location.y + relative_y) >= self.bounds.ymax)
-            {
-                return Some(position);
+        let now = Instant::now();
+        let Some((edge, offset, length)) = self.edge_at(x, y) else {
+            self.gate.leave(now);
+            return None;
+        };
+        if self.gate.in_dead_zone(offset, length) {
+            return None;
+        }
+        if self
+            .gate
+            .blocked(&edge, buttons_held(), key_held, display_captured())
+        {
+            return None;
+        }
+        // the location stays at the bounds, the delta is the push past them
+        let distance = match edge.position {
+            Position::Left => -relative_x,
+            Position::Right => relative_x,
+            Position::Top => -relative_y,
+            Position::Bottom => relative_y,
+        };
+        self.gate.push(distance, now).then_some(edge)
+    }
+
+    // the edge `x`, `y` is at or past, with how far along the edge it is and the edge's length
+    fn edge_at(&self, x: f64, y: f64) -> Option<(Edge, f64, f64)> {
+        let Bounds {
+            xmin,
+            xmax,
+            ymin,
+            ymax,
+        } = self.bounds;
+
+        self.active_clients.iter().find_map(|edge| {
+            let crossed = match edge.position {
+                Position::Left => x <= xmin,
+                Position::Right => x >= xmax,
+                Position::Top => y <= ymin,
+                Position::Bottom => y >= ymax,
+            };
+            // how far along the edge the pointer crossed it
+            let (offset, length) = match edge.position {
+                Position::Left | Position::Right => (y - ymin, ymax - ymin),
+                Position::Top | Position::Bottom => (x - xmin, xmax - xmin),
+            };
+            (crossed && edge.contains(offset / length)).then(|| (edge.clone(), offset, length))
+        })
+    }
+
+    // files dragged to an edge and dropped there, told by the drag pasteboard
+    // changing between pressing and releasing the left button
+    fn dropped(
+        &mut self,
+        event_type: &CGEventType,
+        event: &CGEvent,
+    ) -> Option<(Edge, Vec<PathBuf>)> {
+        match event_type {
+            CGEventType::LeftMouseDown => {
+                self.drag_count = Some(drag_count());
+                self.dragging = None;
+            }
+            CGEventType::LeftMouseDragged => {
+                let location = event.location();
+                let relative_x = event.get_double_value_field(EventField::MOUSE_EVENT_DELTA_X);
+                let relative_y = event.get_double_value_field(EventField::MOUSE_EVENT_DELTA_Y);
+                self.dragging = self
+                    .edge_at(location.x + relative_x, location.y + relative_y)
+                    .map(|(edge, ..)| edge);
+            }
+            CGEventType::LeftMouseUp => {
+                let dragged = self
+                    .drag_count
+                    .take()
+                    .is_some_and(|count| count != drag_count());
+                if let Some(edge) = self.dragging.take()
+                    && dragged
+                {
+                    let files = dragged_files();
+                    return (!files.is_empty()).then_some((edge, files));
+                }
             }
+            _ => {}
         }
         None
     }
@@ -101,7 +208,7 @@ impl InputCaptureState {
     // to the edge of the screen, the cursor will be hidden but we dont want it to appear in a
     // random location when we exit the client
     fn reset_mouse_position(&self, event: &CGEvent) -> Result<()> {
-        if let Some(pos) = self.current_pos {
+        if let Some(edge) = &self.current_pos {
             let location = event.location();
             let edge_offset = 1.0;
 
@@ -113,7 +220,7 @@ impl InputCaptureState {
             let mut new_x = location.x + delta_x;
             let mut new_y = location.y + delta_y;
 
-            match pos {
+            match edge.position {
                 Position::Left => {
                     new_x = self.bounds.xmin + edge_offset;
                 }
@@ -146,28 +253,37 @@ impl InputCaptureState {
             ProducerEvent::Grab(pos) => {
                 if self.current_pos.is_none() {
                     CGDisplay::hide_cursor(&CGDisplay::main()).map_err(Report::msg)?;
-                    self.current_pos = Some(pos);
                 }
+                // a grab through `grab` may switch from one client to another
+                self.current_pos = Some(pos);
             }
             ProducerEvent::Create(p) => {
                 self.active_clients.insert(p);
             }
             ProducerEvent::Destroy(p) => {
-                if let Some(current) = self.current_pos {
-                    if current == p {
-                        CGDisplay::show_cursor(&CGDisplay::main()).map_err(Report::msg)?;
-                        self.current_pos = None;
-                    };
+                if self.current_pos.as_ref() == Some(&p) {
+                    CGDisplay::show_cursor(&CGDisplay::main()).map_err(Report::msg)?;
+                    self.current_pos = None;
                 }
+                self.gate.remove_block(&p);
                 self.active_clients.remove(&p);
             }
+            ProducerEvent::SetKeyMode(mode) => self.key_mode = mode,
+            ProducerEvent::SetSwitchPolicy(policy) => self.gate.set_policy(policy),
+            ProducerEvent::SetSwitchBlock(edge, block) => self.gate.set_block(edge, block),
             ProducerEvent::EventTapDisabled => return Err(Report::msg("EventTapDisabled")),
         };
         Ok(())
     }
 }
 
-fn get_events(ev_type: &CGEventType, ev: &CGEvent, result: &mut Vec<CaptureEvent>) -> Result<()> {
+fn get_events(
+    ev_type: &CGEventType,
+    ev: &CGEvent,
+    key_mode: KeyMode,
+    pressed_keysyms: &mut HashMap<u32, u32>,
+    result: &mut Vec<CaptureEvent>,
+) -> Result<()> {
     fn map_pointer_event(ev: &CGEvent) -> PointerEvent {
         PointerEvent::Motion {
             time: 0,
@@ -176,58 +292,77 @@ fn get_events(ev_type: &CGEventType, ev: &CGEvent, result: &mut Vec<CaptureEvent
         }
     }
 
+    // the character typed by a key event, according to the local layout
+    fn key_char(ev: &CGEvent) -> Option<char> {
+        let mut buf = [0u16; 4];
+        let mut len = 0;
+        unsafe {
+            CGEventKeyboardGetUnicodeString(ev.as_ptr(), buf.len() as _, &mut len, buf.as_mut_ptr())
+        };
+        let mut chars = char::decode_utf16(buf[..len as usize].iter().copied());
+        match (chars.next(), chars.next()) {
+            (Some(Ok(c)), None) => Some(c),
+            _ => None,
+        }
+    }
+
     fn map_key(ev: &CGEvent) -> Result<u32> {
         let code = ev.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE);
-        match KeyMap::from_key_mapping(KeyMapping::Mac(code as u16)) {
-            Ok(k) => Ok(k.evdev as u32),
-            Err(()) => Err(Report::msg(format!("KeyMapError({})", code))),
+        match scancode::MacOS::try_from(code as u16) {
+            Ok(k) => Ok(scancode::Linux::from(k) as u32),
+            Err(_) => Err(Report::msg(format!("KeyMapError({})", code))),
         }
     }
 
     match ev_type {
         CGEventType::KeyDown => {
             let k = map_key(ev)?;
-            result.push(CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key {
-                time: 0,
-                key: k,
-                state: 1,
-            })));
+            let c = key_char(ev).filter(|&c| keysym::is_printable(c));
+            let event = match c {
+                Some(c) if key_mode == KeyMode::Keysym => {
+                    let keysym = keysym::from_char(c);
+                    pressed_keysyms.insert(k, keysym);
+                    KeyboardEvent::Keysym {
+                        time: 0,
+                        key: k,
+                        keysym,
+                        state: 1,
+                    }
+                }
+                _ => KeyboardEvent::Key {
+                    time: 0,
+                    key: k,
+                    state: 1,
+                },
+            };
+            result.push(CaptureEvent::Input(Event::Keyboard(event)));
         }
         CGEventType::KeyUp => {
             let k = map_key(ev)?;
-            result.push(CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key {
-                time: 0,
-                key: k,
-                state: 0,
-            })));
+            // released as it was pressed, whatever the modifiers are now
+            let event = match pressed_keysyms.remove(&k) {
+                Some(keysym) => KeyboardEvent::Keysym {
+                    time: 0,
+                    key: k,
+                    keysym,
+                    state: 0,
+                },
+                None => KeyboardEvent::Key {
+                    time: 0,
+                    key: k,
+                    state: 0,
+                },
+            };
+            result.push(CaptureEvent::Input(Event::Keyboard(event)));
         }
         CGEventType::FlagsChanged => {
-            let mut mods = XMods::empty();
-            let mut mods_locked = XMods::empty();
-            let cg_flags = ev.get_flags();
-
-            if cg_flags.contains(CGEventFlags::CGEventFlagShift) {
-                mods |= XMods::ShiftMask;
-            }
-            if cg_flags.contains(CGEventFlags::CGEventFlagControl) {
-                mods |= XMods::ControlMask;
-            }
-            if cg_flags.contains(CGEventFlags::CGEventFlagAlternate) {
-                mods |= XMods::Mod1Mask;
-            }
-            if cg_flags.contains(CGEventFlags::CGEventFlagCommand) {
-                mods |= XMods::Mod4Mask;
-            }
-            if cg_flags.contains(CGEventFlags::CGEventFlagAlphaShift) {
-                mods |= XMods::LockMask;
-                mods_locked |= XMods::LockMask;
-            }
-
+            let mods = Modifiers::from_cg_flags(ev.get_flags().bits());
+            let (depressed, latched, locked, group) = mods.xkb_masks();
             let modifier_event = KeyboardEvent::Modifiers {
-                depressed: mods.bits(),
-                latched: 0,
-                locked: mods_locked.bits(),
-                group: 0,
+                depressed,
+                latched,
+                locked,
+                group,
             };
 
             result.push(CaptureEvent::Input(Event::Keyboard(modifier_event)));
@@ -273,46 +408,94 @@ fn get_events(ev_type: &CGEventType, ev: &CGEvent, result: &mut Vec<CaptureEvent
             })))
         }
         CGEventType::OtherMouseDown => {
-            result.push(CaptureEvent::Input(Event::Pointer(PointerEvent::Button {
-                time: 0,
-                button: BTN_MIDDLE,
-                state: 1,
-            })))
+            let number = ev.get_integer_value_field(EventField::MOUSE_EVENT_BUTTON_NUMBER);
+            if let Some(&button) = BUTTONS.get(number as usize) {
+                result.push(CaptureEvent::Input(Event::Pointer(PointerEvent::Button {
+                    time: 0,
+                    button,
+                    state: 1,
+                })))
+            }
         }
         CGEventType::OtherMouseUp => {
-            result.push(CaptureEvent::Input(Event::Pointer(PointerEvent::Button {
-                time: 0,
-                button: BTN_MIDDLE,
-                state: 0,
-            })))
+            let number = ev.get_integer_value_field(EventField::MOUSE_EVENT_BUTTON_NUMBER);
+            if let Some(&button) = BUTTONS.get(number as usize) {
+                result.push(CaptureEvent::Input(Event::Pointer(PointerEvent::Button {
+                    time: 0,
+                    button,
+                    state: 0,
+                })))
+            }
         }
         CGEventType::ScrollWheel => {
-            let v = ev.get_integer_value_field(EventField::SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_1);
-            let h = ev.get_integer_value_field(EventField::SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_2);
-            if v != 0 {
+            let continuous =
+                ev.get_integer_value_field(EventField::SCROLL_WHEEL_EVENT_IS_CONTINUOUS) != 0;
+            let phase = ev.get_integer_value_field(SCROLL_WHEEL_EVENT_SCROLL_PHASE);
+            let momentum = ev.get_integer_value_field(SCROLL_WHEEL_EVENT_MOMENTUM_PHASE);
+
+            // momentum events are the kinetic scrolling computed after the fingers were lifted,
+            // they are forwarded as continuous scrolling
+            let source = if !continuous {
+                AxisSource::Wheel
+            } else if phase != 0 && momentum == 0 {
+                AxisSource::Finger
+            } else {
+                AxisSource::Continuous
+            };
+
+            // the point delta fields are whole pixels, the fixed point ones keep
+            // the fraction but count lines
+            let v = ev
+                .get_double_value_field(EventField::SCROLL_WHEEL_EVENT_FIXED_POINT_DELTA_AXIS_1)
+                * PIXELS_PER_LINE;
+            let h = ev
+                .get_double_value_field(EventField::SCROLL_WHEEL_EVENT_FIXED_POINT_DELTA_AXIS_2)
+                * PIXELS_PER_LINE;
+            let stop = matches!(phase, SCROLL_PHASE_ENDED | SCROLL_PHASE_CANCELLED);
+            if v != 0. || h != 0. || stop {
+                result.push(CaptureEvent::Input(Event::Pointer(
+                    PointerEvent::AxisSource { source },
+                )));
+            }
+            if v != 0. {
                 result.push(CaptureEvent::Input(Event::Pointer(PointerEvent::Axis {
                     time: 0,
                     axis: 0, // Vertical
-                    value: v as f64,
+                    value: v,
                 })));
             }
-            if h != 0 {
+            if h != 0. {
                 result.push(CaptureEvent::Input(Event::Pointer(PointerEvent::Axis {
                     time: 0,
                     axis: 1, // Horizontal
-                    value: h as f64,
+                    value: h,
                 })));
             }
+            if stop {
+                for axis in [0, 1] {
+                    result.push(CaptureEvent::Input(Event::Pointer(
+                        PointerEvent::AxisStop { time: 0, axis },
+                    )));
+                }
+            }
         }
         _ => (),
     }
+
+    // every CGEvent is a frame of its own
+    if result
+        .iter()
+        .any(|e| matches!(e, CaptureEvent::Input(Event::Pointer(_))))
+    {
+        result.push(CaptureEvent::Input(Event::Pointer(PointerEvent::Frame)));
+    }
     Ok(())
 }
 
 fn create_event_tap<'a>(
     client_state: Arc<Mutex<InputCaptureState>>,
     notify_tx: Sender<ProducerEvent>,
-    event_tx: Sender<(Position, CaptureEvent)>,
+    event_tx: Sender<(Edge, CaptureEvent)>,
 ) -> Result<CGEventTap<'a>> {
     let cg_events_of_interest: Vec<CGEventType> = vec![
         CGEventType::LeftMouseDown,
@@ -349,9 +532,21 @@ fn create_event_tap<'a>(
             }
 
             // Are we in a client?
-            if let Some(current_pos) = state.current_pos {
-                pos = Some(current_pos);
-                get_events(&event_type, cg_ev, &mut res_events).unwrap_or_else(|e| {
+            if let Some(current_pos) = &state.current_pos {
+                pos = Some(current_pos.clone());
+                let InputCaptureState {
+                    key_mode,
+                    pressed_keysyms,
+                    ..
+                } = &mut *state;
+                get_events(
+                    &event_type,
+                    cg_ev,
+                    *key_mode,
+                    pressed_keysyms,
+                    &mut res_events,
+                )
+                .unwrap_or_else(|e| {
                     eprintln!("Failed to get events: {e}");
                 });
 
@@ -365,18 +560,24 @@ fn create_event_tap<'a>(
             // Did we cross a barrier?
             else if matches!(event_type, CGEventType::MouseMoved) {
                 if let Some(new_pos) = state.crossed(cg_ev) {
-                    pos = Some(new_pos);
+                    pos = Some(new_pos.clone());
                     res_events.push(CaptureEvent::Begin);
                     notify_tx
                         .blocking_send(ProducerEvent::Grab(new_pos))
                         .expect("Failed to send notification");
                 }
             }
+            // Were files dropped on a barrier? They still go wherever the cursor is.
+            else if let Some((edge, files)) = state.dropped(&event_type, cg_ev) {
+                event_tx
+                    .blocking_send((edge, CaptureEvent::Dropped(files)))
+                    .expect("Failed to send event");
+            }
 
             if let Some(pos) = pos {
                 res_events.iter().for_each(|e| {
                     event_tx
-                        .blocking_send((pos, *e))
+                        .blocking_send((pos.clone(), e.clone()))
                         .expect("Failed to send event");
                 });
                 // Returning None should stop the event from being processed
@@ -410,9 +611,9 @@ fn create_event_tap<'a>(
 
 fn event_tap_thread(
     client_state: Arc<Mutex<InputCaptureState>>,
-    event_tx: Sender<(Position, CaptureEvent)>,
+    event_tx: Sender<(Edge, CaptureEvent)>,
     notify_tx: Sender<ProducerEvent>,
-    ready: std::sync::mpsc::Sender<Result<()>>,
+    ready: std::sync::mpsc::Sender<Result<CFRunLoop>>,
     exit: oneshot::Sender<Result<(), &'static str>>,
 ) {
     let _tap = match create_event_tap(client_state, notify_tx, event_tx) {
@@ -421,7 +622,9 @@ fn event_tap_thread(
             return;
         }
         Ok(tap) => {
-            ready.send(Ok(())).expect("channel closed");
+            ready
+                .send(Ok(CFRunLoop::get_current()))
+                .expect("channel closed");
             tap
         }
     };
@@ -431,8 +634,11 @@ fn event_tap_thread(
 }
 
 pub struct MacOSInputCapture {
-    event_rx: Receiver<(Position, CaptureEvent)>,
+    event_rx: Receiver<(Edge, CaptureEvent)>,
     notify_tx: Sender<ProducerEvent>,
+    state: Arc<Mutex<InputCaptureState>>,
+    // run loop of the event tap thread
+    run_loop: CFRunLoop,
 }
 
 impl MacOSInputCapture {
@@ -449,6 +655,7 @@ impl MacOSInputCapture {
 
         println!("Enabling CGEvent tap");
         let event_tap_thread_state = state.clone();
+        let producer_state = state.clone();
         let event_tap_notify = notify_tx.clone();
         thread::spawn(move || {
             event_tap_thread(
@@ -460,14 +667,14 @@ impl MacOSInputCapture {
             )
         });
 
-        ready_rx.recv().expect("channel closed")?;
+        let run_loop = ready_rx.recv().expect("channel closed")?;
 
         let _tap_task: tokio::task::JoinHandle<()> = tokio::task::spawn(async move {
             loop {
                 tokio::select! {
                     producer_event = notify_rx.recv() => {
                         let producer_event = producer_event.expect("channel closed");
-                        let mut state = state.lock().await;
+                        let mut state = producer_state.lock().await;
                         state.handle_producer_event(producer_event).await.unwrap_or_else(|e| {
                             eprintln!("Failed to handle producer event: {e}");
                         })
@@ -486,12 +693,19 @@ impl MacOSInputCapture {
         Ok(Self {
             event_rx,
             notify_tx,
+            state,
+            run_loop,
         })
     }
 }
 
 impl MacOSInputCapture {
-    pub async fn create(&mut self, pos: Position) -> Result<()> {
+    pub async fn create(&mut self, pos: Edge) -> Result<()> {
+        if let Some(output) = &pos.output {
+            return Err(Report::msg(format!(
+                "macOS displays have no names, edges span all of them: remove output {output}"
+            )));
+        }
         let notify_tx = self.notify_tx.clone();
         tokio::task::spawn(async move {
             println!("creating capture, {:?}", pos);
@@ -501,7 +715,7 @@ impl MacOSInputCapture {
         Ok(())
     }
 
-    pub async fn destroy(&mut self, pos: Position) -> Result<()> {
+    pub async fn destroy(&mut self, pos: Edge) -> Result<()> {
         let notify_tx = self.notify_tx.clone();
         tokio::task::spawn(async move {
             println!("destroying capture {:?}", pos);
@@ -511,6 +725,30 @@ impl MacOSInputCapture {
         Ok(())
     }
 
+    pub async fn set_key_mode(&mut self, mode: KeyMode) {
+        let _ = self.notify_tx.send(ProducerEvent::SetKeyMode(mode)).await;
+    }
+
+    pub async fn set_switch_policy(&mut self, policy: SwitchPolicy) {
+        let _ = self
+            .notify_tx
+            .send(ProducerEvent::SetSwitchPolicy(policy))
+            .await;
+    }
+
+    pub async fn set_switch_block(&mut self, edge: Edge, block: SwitchBlock) -> Result<()> {
+        let _ = self
+            .notify_tx
+            .send(ProducerEvent::SetSwitchBlock(edge, block))
+            .await;
+        Ok(())
+    }
+
+    pub async fn grab(&mut self, pos: Edge) -> Result<()> {
+        let _ = self.notify_tx.send(ProducerEvent::Grab(pos)).await;
+        Ok(())
+    }
+
     pub async fn release(&mut self) -> Result<()> {
         let notify_tx = self.notify_tx.clone();
         tokio::task::spawn(async move {
@@ -520,13 +758,52 @@ impl MacOSInputCapture {
         Ok(())
     }
 
+    // the cursor is shown again before the event tap goes away with its thread
     pub async fn terminate(&mut self) -> Result<()> {
+        self.state
+            .lock()
+            .await
+            .handle_producer_event(ProducerEvent::Release)
+            .await?;
+        self.run_loop.stop();
         Ok(())
     }
+
+    // there is no XKB keymap to share, peers keep their own layout
+    pub fn keymap(&self) -> Option<Keymap> {
+        None
+    }
+
+    // CoreGraphics doesn't expose the user's key repeat, peers use their configured repeat
+    pub fn repeat_info(&self) -> Option<RepeatInfo> {
+        None
+    }
+
+    pub fn locks(&self) -> Option<u32> {
+        let flags = unsafe { CGEventSourceFlagsState(CGEventSourceStateID::HIDSystemState) };
+        Some(Modifiers::from_cg_flags(flags).locked.bits())
+    }
+
+    pub fn screens(&self) -> Vec<Screen> {
+        CGDisplay::active_displays()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| {
+                let bounds = CGDisplay::new(id).bounds();
+                Screen {
+                    name: None,
+                    x: bounds.origin.x as i32,
+                    y: bounds.origin.y as i32,
+                    width: bounds.size.width as i32,
+                    height: bounds.size.height as i32,
+                }
+            })
+            .collect()
+    }
 }
 
 impl Stream for MacOSInputCapture {
-    type Item = Result<(Position, CaptureEvent)>;
+    type Item = Result<(Edge, CaptureEvent)>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         match ready!(self.event_rx.poll_recv(cx)) {
@@ -537,6 +814,7 @@ impl Stream for MacOSInputCapture {
 }
 
 type CGSConnectionID = u32;
+type UniCharCount = c_ulong;
 
 #[link(name = "ApplicationServices", kind = "framework")]
 unsafe extern "C" {
@@ -550,12 +828,97 @@ unsafe extern "C" {
 }
 
 unsafe extern "C" {
+    fn CGEventKeyboardGetUnicodeString(
+        event: CGEventRef,
+        max_len: UniCharCount,
+        actual_len: *mut UniCharCount,
+        string: *mut u16,
+    );
+    fn CGEventSourceFlagsState(state_id: CGEventSourceStateID) -> u64;
+    fn CGEventSourceButtonState(state_id: CGEventSourceStateID, button: u32) -> bool;
+    fn CGEventSourceKeyState(state_id: CGEventSourceStateID, key: u16) -> bool;
+    fn CGDisplayIsCaptured(display: u32) -> u32;
     fn CGEventSourceSetLocalEventsSuppressionInterval(
         event_source: CGEventSource,
         seconds: CFTimeInterval,
     );
 }
 
+// NSPasteboardNameDrag, written by whoever starts a drag
+unsafe fn drag_pasteboard() -> *mut c_void {
+    unsafe {
+        send1(
+            class(c"NSPasteboard"),
+            c"pasteboardWithName:",
+            ns_string(c"Apple CFPasteboard drag"),
+        )
+    }
+}
+
+fn drag_count() -> isize {
+    unsafe {
+        let pool = objc_autoreleasePoolPush();
+        let count = send(drag_pasteboard(), c"changeCount");
+        objc_autoreleasePoolPop(pool);
+        count
+    }
+}
+
+// files on the drag pasteboard
+fn dragged_files() -> Vec<PathBuf> {
+    let mut files = vec![];
+    unsafe {
+        let pool = objc_autoreleasePoolPush();
+        let items: *mut c_void = send(drag_pasteboard(), c"pasteboardItems");
+        let count: usize = if items.is_null() {
+            0
+        } else {
+            send(items, c"count")
+        };
+        for i in 0..count {
+            let item: *mut c_void = send1(items, c"objectAtIndex:", i);
+            let string: *mut c_void = send1(item, c"stringForType:", ns_string(c"public.file-url"));
+            if string.is_null() {
+                continue;
+            }
+            // Finder drags file reference URLs, file:///.file/id=...
+            let url: *mut c_void = send1(class(c"NSURL"), c"URLWithString:", string);
+            let url: *mut c_void = match url.is_null() {
+                true => continue,
+                false => send(url, c"filePathURL"),
+            };
+            if url.is_null() {
+                continue;
+            }
+            let path: *mut c_void = send(url, c"path");
+            let path: *const c_char = send(path, c"UTF8String");
+            files.push(PathBuf::from(
+                CStr::from_ptr(path).to_string_lossy().into_owned(),
+            ));
+        }
+        objc_autoreleasePoolPop(pool);
+    }
+    files
+}
+
+// left, right or middle button
+fn buttons_held() -> bool {
+    (0..3).any(|button| unsafe {
+        CGEventSourceButtonState(CGEventSourceStateID::HIDSystemState, button)
+    })
+}
+
+fn key_held(key: scancode::Linux) -> bool {
+    scancode::MacOS::try_from(key).is_ok_and(|key| unsafe {
+        CGEventSourceKeyState(CGEventSourceStateID::HIDSystemState, key as u16)
+    })
+}
+
+// games going fullscreen capture the display, fullscreen spaces can't be told apart
+fn display_captured() -> bool {
+    unsafe { CGDisplayIsCaptured(CGDisplay::main().id) != 0 }
+}
+
 unsafe fn configure_cf_settings() -> Result<()> {
     // When we warp the cursor using CGWarpMouseCursorPosition local events are suppressed for a short time
     // this leeds to the cursor not flowing when crossing back from a clinet, set this to to 0 stops the warp
@@ -592,19 +955,3 @@ unsafe fn configure_cf_settings() -> Result<()> {
 
     Ok(())
 }
-
-// From X11/X.h
-bitflags! {
-    #[repr(C)]
-    #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-    struct XMods: u32 {
-        const ShiftMask = (1<<0);
-        const LockMask = (1<<1);
-        const ControlMask = (1<<2);
-        const Mod1Mask = (1<<3);
-        const Mod2Mask = (1<<4);
-        const Mod3Mask = (1<<5);
-        const Mod4Mask = (1<<6);
-        const Mod5Mask = (1<<7);
-    }
-}
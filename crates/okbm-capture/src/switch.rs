@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{Edge, scancode::Linux};
+
+/// When reaching an edge switches to the peer behind it, configured as
+///
+/// ```toml
+/// [switching]
+/// corner_dead_zone = 40
+/// push_distance = 30
+/// dwell_ms = 0
+/// double_tap_ms = 0
+/// ```
+///
+/// By default reaching an edge switches right away.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwitchPolicy {
+    // pixels from either end of an edge where it doesn't switch
+    pub corner_dead_zone: u32,
+    // distance the pointer has to be pushed past the edge
+    pub push_distance: u32,
+    // time the pointer has to be pushed against the edge
+    pub dwell_ms: u64,
+    // only switch when the edge is reached again within this time of leaving it, 0 disables
+    pub double_tap_ms: u64,
+}
+
+/// What keeps an edge from switching, configured per peer as
+///
+/// ```toml
+/// block = { buttons = true, keys = ["KeyLeftShift"], fullscreen = true }
+/// ```
+///
+/// Held keys only block on macOS. On Wayland the capture windows get keyboard focus
+/// once input is captured, not while the pointer reaches the edge, so the keys held
+/// then are unknown, modifiers included: `keys` is rejected there. macOS only tells
+/// about games capturing the display, not fullscreen spaces.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwitchBlock {
+    // while a pointer button is held, e.g. when dragging a window
+    pub buttons: bool,
+    // while any of these keys is held, macOS only
+    pub keys: Vec<Linux>,
+    // while a fullscreen window has focus
+    pub fullscreen: bool,
+}
+
+impl Default for SwitchBlock {
+    fn default() -> Self {
+        Self {
+            buttons: true,
+            keys: vec![],
+            fullscreen: false,
+        }
+    }
+}
+
+/// Tracks the pointer against an edge until the policy lets it switch.
+/// Without pointer motion nothing happens, dwelling means pushing long enough.
+#[derive(Debug, Default)]
+pub(crate) struct SwitchGate {
+    policy: SwitchPolicy,
+    blocks: HashMap<Edge, SwitchBlock>,
+    // when the pointer reached the edge and how far it was pushed since
+    reached: Option<Instant>,
+    pushed: f64,
+    // the edge was left shortly before being reached again
+    tapped: bool,
+    left: Option<Instant>,
+}
+
+impl SwitchGate {
+    pub(crate) fn set_policy(&mut self, policy: SwitchPolicy) {
+        self.policy = policy;
+        self.reset();
+    }
+
+    pub(crate) fn set_block(&mut self, edge: Edge, block: SwitchBlock) {
+        self.blocks.insert(edge, block);
+    }
+
+    pub(crate) fn remove_block(&mut self, edge: &Edge) {
+        self.blocks.remove(edge);
+    }
+
+    /// Whether switching through `edge` is blocked by what is held or focused,
+    /// pushing against it starts over once it isn't.
+    pub(crate) fn blocked(
+        &mut self,
+        edge: &Edge,
+        buttons: bool,
+        key_held: impl Fn(Linux) -> bool,
+        fullscreen: bool,
+    ) -> bool {
+        let default = SwitchBlock::default();
+        let block = self.blocks.get(edge).unwrap_or(&default);
+        let blocked = (block.buttons && buttons)
+            || block.keys.iter().any(|&key| key_held(key))
+            || (block.fullscreen && fullscreen);
+        if blocked {
+            self.reached = None;
+        }
+        blocked
+    }
+
+    /// Whether `offset` pixels along an edge `length` long is too close to a corner,
+    /// pushing starts over once out of it. The pointer is still at the edge,
+    /// it doesn't count as leaving it for a double tap.
+    pub(crate) fn in_dead_zone(&mut self, offset: f64, length: f64) -> bool {
+        let zone = self.policy.corner_dead_zone as f64;
+        let inside = offset < zone || offset > length - zone;
+        if inside {
+            self.reached = None;
+        }
+        inside
+    }
+
+    /// The pointer is against the edge and was pushed `distance` further past it,
+    /// returns whether to switch.
+    pub(crate) fn push(&mut self, distance: f64, now: Instant) -> bool {
+        let reached = *self.reached.get_or_insert_with(|| {
+            let window = Duration::from_millis(self.policy.double_tap_ms);
+            self.tapped = self.left.is_some_and(|left| now - left <= window);
+            self.pushed = 0.;
+            now
+        });
+        self.pushed += distance.max(0.);
+
+        let switch = self.pushed >= self.policy.push_distance as f64
+            && now - reached >= Duration::from_millis(self.policy.dwell_ms)
+            && (self.policy.double_tap_ms == 0 || self.tapped);
+        if switch {
+            self.reset();
+        }
+        switch
+    }
+
+    /// The pointer left the edge.
+    pub(crate) fn leave(&mut self, now: Instant) {
+        if self.reached.take().is_some() {
+            self.left = Some(now);
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.reached = None;
+        self.pushed = 0.;
+        self.tapped = false;
+        self.left = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn gate(policy: SwitchPolicy) -> SwitchGate {
+        let mut gate = SwitchGate::default();
+        gate.set_policy(policy);
+        gate
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn switches_right_away_by_default() {
+        let mut gate = SwitchGate::default();
+        assert!(!gate.in_dead_zone(0., 100.));
+        assert!(gate.push(0., Instant::now()));
+    }
+
+    #[test]
+    fn dead_zone() {
+        let mut gate = gate(SwitchPolicy {
+            corner_dead_zone: 10,
+            ..Default::default()
+        });
+        assert!(gate.in_dead_zone(9., 100.));
+        assert!(gate.in_dead_zone(91., 100.));
+        assert!(!gate.in_dead_zone(10., 100.));
+        assert!(!gate.in_dead_zone(90., 100.));
+    }
+
+    #[test]
+    fn push_distance_adds_up() {
+        let mut gate = gate(SwitchPolicy {
+            push_distance: 30,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert!(!gate.push(10., now));
+        // moving back doesn't undo the push
+        assert!(!gate.push(-50., now));
+        assert!(!gate.push(10., now));
+        assert!(gate.push(10., now));
+        // and it starts over after switching
+        assert!(!gate.push(10., now));
+    }
+
+    #[test]
+    fn push_starts_over_in_the_dead_zone() {
+        let mut gate = gate(SwitchPolicy {
+            corner_dead_zone: 10,
+            push_distance: 30,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert!(!gate.push(20., now));
+        assert!(gate.in_dead_zone(0., 100.));
+        assert!(!gate.push(20., now));
+        assert!(gate.push(10., now));
+    }
+
+    #[test]
+    fn dwell() {
+        let mut gate = gate(SwitchPolicy {
+            dwell_ms: 100,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        assert!(!gate.push(1., start));
+        assert!(!gate.push(1., start + ms(99)));
+        assert!(gate.push(1., start + ms(100)));
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut gate = gate(SwitchPolicy {
+            double_tap_ms: 300,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        assert!(!gate.push(1., start));
+        gate.leave(start + ms(50));
+        assert!(gate.push(1., start + ms(300)));
+
+        // too late
+        assert!(!gate.push(1., start + ms(1000)));
+        gate.leave(start + ms(1000));
+        assert!(!gate.push(1., start + ms(1400)));
+    }
+
+    #[test]
+    fn corners_are_not_taps() {
+        let mut gate = gate(SwitchPolicy {
+            corner_dead_zone: 10,
+            double_tap_ms: 300,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        assert!(!gate.push(1., start));
+        assert!(gate.in_dead_zone(5., 100.));
+        assert!(!gate.push(1., start + ms(50)));
+    }
+
+    #[test]
+    fn blocks() {
+        let mut gate = SwitchGate::default();
+        let edge = Edge::from(Position::Left);
+        assert!(gate.blocked(&edge, true, |_| false, false));
+        assert!(!gate.blocked(&edge, false, |_| false, true));
+
+        gate.set_block(
+            edge.clone(),
+            SwitchBlock {
+                buttons: false,
+                keys: vec![Linux::KeyLeftShift],
+                fullscreen: true,
+            },
+        );
+        assert!(!gate.blocked(&edge, true, |_| false, false));
+        assert!(gate.blocked(&edge, false, |key| key == Linux::KeyLeftShift, false));
+        assert!(gate.blocked(&edge, false, |_| false, true));
+
+        // a destroyed edge created again starts from the default
+        gate.remove_block(&edge);
+        assert!(gate.blocked(&edge, true, |_| false, false));
+        assert!(!gate.blocked(&edge, false, |_| false, true));
+    }
+}
@@ -1,21 +1,26 @@
 use futures::Stream;
 
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     env,
     fmt::{self, Display},
     io::{self, ErrorKind},
-    os::fd::{AsFd, RawFd},
+    os::fd::{AsFd, OwnedFd, RawFd},
     pin::Pin,
     task::{Context, Poll, ready},
 };
-use tokio::io::unix::AsyncFd;
+use tokio::{
+    io::unix::AsyncFd,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
 
 use std::{
     fs::File,
-    io::{BufWriter, Write},
-    os::unix::prelude::AsRawFd,
+    io::{BufWriter, Read, Write},
+    os::unix::{fs::FileExt, prelude::AsRawFd},
     sync::Arc,
+    thread,
+    time::Instant,
 };
 
 use wayland_protocols::{
@@ -39,18 +44,27 @@ use wayland_protocols::{
     },
 };
 
-use wayland_protocols_wlr::layer_shell::v1::client::{
-    zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
-    zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+use wayland_protocols_wlr::{
+    foreign_toplevel::v1::client::{
+        zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+        zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+    },
+    layer_shell::v1::client::{
+        zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+        zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+    },
 };
 
 use wayland_client::{
-    Connection, Dispatch, DispatchError, EventQueue, QueueHandle, WEnum,
-    backend::{ReadEventsGuard, WaylandError},
-    delegate_noop,
+    Connection, Dispatch, DispatchError, EventQueue, Proxy, QueueHandle, WEnum,
+    backend::{ObjectId, ReadEventsGuard, WaylandError},
+    delegate_noop, event_created_child,
     globals::{Global, GlobalList, GlobalListContents, registry_queue_init},
     protocol::{
         wl_buffer, wl_compositor,
+        wl_data_device::{self, WlDataDevice},
+        wl_data_device_manager::{DndAction, WlDataDeviceManager},
+        wl_data_offer::{self, WlDataOffer},
         wl_keyboard::{self, WlKeyboard},
         wl_output::{self, WlOutput},
         wl_pointer::{self, WlPointer},
@@ -63,11 +77,15 @@ use wayland_client::{
 
 use crate::*;
 
+// the MIME type files are dragged as
+const URI_LIST: &str = "text/uri-list";
+
 struct Globals {
     compositor: wl_compositor::WlCompositor,
     pointer_constraints: ZwpPointerConstraintsV1,
     relative_pointer_manager: ZwpRelativePointerManagerV1,
     shortcut_inhibit_manager: Option<ZwpKeyboardShortcutsInhibitManagerV1>,
+    toplevel_manager: Option<ZwlrForeignToplevelManagerV1>,
     seat: wl_seat::WlSeat,
     shm: wl_shm::WlShm,
     layer_shell: ZwlrLayerShellV1,
@@ -106,7 +124,7 @@ struct OutputInfo {
 }
 
 struct State {
-    active_positions: HashSet<Position>,
+    active_edges: HashSet<Edge>,
     pointer: Option<WlPointer>,
     keyboard: Option<WlKeyboard>,
     pointer_lock: Option<ZwpLockedPointerV1>,
@@ -114,19 +132,54 @@ struct State {
     shortcut_inhibitor: Option<ZwpKeyboardShortcutsInhibitorV1>,
     active_windows: Vec<Arc<Window>>,
     focused: Option<Arc<Window>>,
+    // surface of the edge the pointer is over before switching, the serial
+    // of its enter event and the pointer's offset along the edge
+    at_edge: Option<(Arc<Window>, u32, f64)>,
+    gate: SwitchGate,
+    // buttons held over the surface of an edge. A button pressed elsewhere, e.g. to
+    // move a window, holds an implicit grab: the pointer doesn't enter our surfaces
+    // until it is released, so those never switch
+    buttons: HashSet<u32>,
+    // a drag and drop of any kind is over the surface of an edge
+    dnd: bool,
+    // toplevels and whether they are focused and fullscreen
+    toplevels: HashMap<ObjectId, bool>,
+    // MIME types of the offers of the data device
+    offers: HashMap<ObjectId, Vec<String>>,
+    // files dragged over the surface of an edge and whether the source lets them be copied
+    drag: Option<(Arc<Window>, WlDataOffer)>,
+    drag_copy: bool,
+    // files dropped on an edge, read from their source on a thread
+    dropped_tx: UnboundedSender<(Edge, CaptureEvent)>,
+    dropped_rx: UnboundedReceiver<(Edge, CaptureEvent)>,
     global_list: GlobalList,
     globals: Globals,
     wayland_fd: RawFd,
     read_guard: Option<ReadEventsGuard>,
     qh: QueueHandle<Self>,
-    pending_events: VecDeque<(Position, CaptureEvent)>,
+    pending_events: VecDeque<(Edge, CaptureEvent)>,
+    // errors of the event handlers, returned by the stream before further events
+    errors: VecDeque<CaptureError>,
     outputs: Vec<Output>,
     scroll_discrete_pending: bool,
+    // XKB keymap of the seat's keyboard and its active layout
+    keymap: Option<String>,
+    group: u32,
+    // locked modifiers of the seat, only known while we have keyboard focus
+    locked: Option<u32>,
+    repeat_info: Option<RepeatInfo>,
+    key_mode: KeyMode,
+    // keymap state resolving keys to characters in keysym mode
+    xkb: Option<xkb::State>,
+    // keysyms sent for the keys currently pressed, released as such
+    pressed_keysyms: HashMap<u32, u32>,
 }
 
 struct Inner {
     state: State,
     queue: EventQueue<State>,
+    // the connection to the compositor is lost
+    closed: bool,
 }
 
 impl AsRawFd for Inner {
@@ -141,7 +194,10 @@ struct Window {
     buffer: wl_buffer::WlBuffer,
     surface: WlSurface,
     layer_surface: ZwlrLayerSurfaceV1,
-    pos: Position,
+    edge: Edge,
+    // offset of the surface along the output's edge and the edge's length
+    offset: u32,
+    length: u32,
 }
 
 impl Window {
@@ -149,18 +205,27 @@ impl Window {
         state: &State,
         qh: &QueueHandle<State>,
         output: &WlOutput,
-        pos: Position,
+        edge: &Edge,
         size: (i32, i32),
-    ) -> Window {
+    ) -> io::Result<Window> {
         println!("creating window output: {output:?}, size: {size:?}");
         let g = &state.globals;
 
-        let (width, height) = match pos {
-            Position::Left | Position::Right => (1, size.1 as u32),
-            Position::Top | Position::Bottom => (size.0 as u32, 1),
+        // the segment's offset from the top or left end of the edge and its length
+        let length = match edge.position {
+            Position::Left | Position::Right => size.1 as u32,
+            Position::Top | Position::Bottom => size.0 as u32,
+        };
+        let offset = length * edge.start.min(100) as u32 / 100;
+        let segment = (length * edge.end.min(100) as u32 / 100)
+            .saturating_sub(offset)
+            .max(1);
+        let (width, height) = match edge.position {
+            Position::Left | Position::Right => (1, segment),
+            Position::Top | Position::Bottom => (segment, 1),
         };
-        let mut file = tempfile::tempfile().unwrap();
-        draw(&mut file, (width, height));
+        let mut file = tempfile::tempfile()?;
+        draw(&mut file, (width, height))?;
         let pool = g
             .shm
             .create_pool(file.as_fd(), (width * height * 4) as i32, qh, ());
@@ -183,24 +248,49 @@ impl Window {
             qh,
             (),
         );
-        let anchor = match pos {
-            Position::Left => Anchor::Left,
-            Position::Right => Anchor::Right,
-            Position::Top => Anchor::Top,
-            Position::Bottom => Anchor::Bottom,
+        // anchored to the start of the edge as well, the margin moves it to the segment
+        let offset = offset as i32;
+        let (anchor, margin) = match edge.position {
+            Position::Left => (Anchor::Left | Anchor::Top, (offset, 0, 0, 0)),
+            Position::Right => (Anchor::Right | Anchor::Top, (offset, 0, 0, 0)),
+            Position::Top => (Anchor::Top | Anchor::Left, (0, 0, 0, offset)),
+            Position::Bottom => (Anchor::Bottom | Anchor::Left, (0, 0, 0, offset)),
         };
 
         layer_surface.set_anchor(anchor);
         layer_surface.set_size(width, height);
         layer_surface.set_exclusive_zone(-1);
-        layer_surface.set_margin(0, 0, 0, 0);
+        layer_surface.set_margin(margin.0, margin.1, margin.2, margin.3);
         surface.set_input_region(None);
         surface.commit();
-        Window {
-            pos,
+        Ok(Window {
+            edge: edge.clone(),
+            offset: offset as u32,
+            length,
             buffer,
             surface,
             layer_surface,
+        })
+    }
+}
+
+impl Window {
+    // offset along the output's edge of a point of the surface
+    fn offset_along(&self, x: f64, y: f64) -> f64 {
+        let along = match self.edge.position {
+            Position::Left | Position::Right => y,
+            Position::Top | Position::Bottom => x,
+        };
+        self.offset as f64 + along
+    }
+
+    // distance a relative motion pushes past the edge
+    fn push_distance(&self, dx: f64, dy: f64) -> f64 {
+        match self.edge.position {
+            Position::Left => -dx,
+            Position::Right => dx,
+            Position::Top => -dy,
+            Position::Bottom => dy,
         }
     }
 }
@@ -233,8 +323,18 @@ fn get_edges(outputs: &[Output], pos: Position) -> Vec<(Output, i32)> {
         .collect()
 }
 
-fn get_output_configuration(state: &State, pos: Position) -> Vec<Output> {
+fn get_output_configuration(state: &State, edge: &Edge) -> Vec<Output> {
+    if let Some(name) = &edge.output {
+        return state
+            .outputs
+            .iter()
+            .filter(|o| o.info.as_ref().is_some_and(|i| &i.name == name))
+            .cloned()
+            .collect();
+    }
+
     // get all output edges corresponding to the position
+    let pos = edge.position;
     let edges = get_edges(&state.outputs, pos);
     let opposite_edges = get_edges(&state.outputs, pos.opposite());
 
@@ -247,19 +347,52 @@ fn get_output_configuration(state: &State, pos: Position) -> Vec<Output> {
         .collect()
 }
 
-fn draw(f: &mut File, (width, height): (u32, u32)) {
+/*
+ * paths of the file:// URIs in a text/uri-list, one per line,
+ * others and comments are skipped
+ */
+fn parse_uri_list(list: &str) -> Vec<PathBuf> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.strip_prefix("file://"))
+        // an optional host before the path
+        .filter_map(|uri| uri.find('/').map(|start| &uri[start..]))
+        .filter_map(percent_decode)
+        .map(PathBuf::from)
+        .collect()
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' && tail.len() >= 2 {
+            let hex = std::str::from_utf8(&tail[..2]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn draw(f: &mut File, (width, height): (u32, u32)) -> io::Result<()> {
     let mut buf = BufWriter::new(f);
     for _ in 0..height {
         for _ in 0..width {
             if env::var("LM_DEBUG_LAYER_SHELL").ok().is_some() {
                 // AARRGGBB
-                buf.write_all(&0xff11d116u32.to_ne_bytes()).unwrap();
+                buf.write_all(&0xff11d116u32.to_ne_bytes())?;
             } else {
                 // AARRGGBB
-                buf.write_all(&0x00000000u32.to_ne_bytes()).unwrap();
+                buf.write_all(&0x00000000u32.to_ne_bytes())?;
             }
         }
     }
+    buf.flush()
 }
 
 impl LayerShellInputCapture {
@@ -287,8 +420,28 @@ impl LayerShellInputCapture {
         }
         let shortcut_inhibit_manager = shortcut_inhibit_manager.ok();
 
+        // only needed to block switching during fullscreen apps
+        let toplevel_manager = global_list.bind(&qh, 2..=3, ());
+        if let Err(e) = &toplevel_manager {
+            println!(
+                "foreign_toplevel_manager not supported: {e}\nfullscreen windows can't block switching"
+            );
+        }
+        let toplevel_manager = toplevel_manager.ok();
+
+        // only needed to drop files on an edge
+        let data_device_manager = global_list.bind::<WlDataDeviceManager, _, _>(&qh, 3..=3, ());
+        if let Err(e) = &data_device_manager {
+            println!("data_device_manager not supported: {e}\nfiles can't be dropped on an edge");
+        }
+        // drags over the surfaces of the edges are told to the seat's data device
+        if let Ok(manager) = data_device_manager {
+            manager.get_data_device(&seat, &qh, ());
+        }
+        let (dropped_tx, dropped_rx) = mpsc::unbounded_channel();
+
         let mut state = State {
-            active_positions: Default::default(),
+            active_edges: Default::default(),
             pointer: None,
             keyboard: None,
             global_list,
@@ -300,6 +453,7 @@ impl LayerShellInputCapture {
                 pointer_constraints,
                 relative_pointer_manager,
                 shortcut_inhibit_manager,
+                toplevel_manager,
                 xdg_output_manager,
             },
             pointer_lock: None,
@@ -307,12 +461,30 @@ impl LayerShellInputCapture {
             shortcut_inhibitor: None,
             active_windows: Vec::new(),
             focused: None,
+            at_edge: None,
+            gate: SwitchGate::default(),
+            buttons: HashSet::new(),
+            dnd: false,
+            toplevels: HashMap::new(),
+            offers: HashMap::new(),
+            drag: None,
+            drag_copy: false,
+            dropped_tx,
+            dropped_rx,
             qh,
             wayland_fd: queue.as_fd().as_raw_fd(),
             read_guard: None,
             pending_events: VecDeque::new(),
+            errors: VecDeque::new(),
             outputs: vec![],
             scroll_discrete_pending: false,
+            keymap: None,
+            group: 0,
+            locked: None,
+            repeat_info: None,
+            key_mode: KeyMode::default(),
+            xkb: None,
+            pressed_keysyms: HashMap::new(),
         };
 
         for global in state.global_list.contents().clone_list() {
@@ -333,33 +505,51 @@ impl LayerShellInputCapture {
         };
         state.read_guard = Some(read_guard);
 
-        let inner = AsyncFd::new(Inner { queue, state })?;
+        let inner = AsyncFd::new(Inner {
+            queue,
+            state,
+            closed: false,
+        })?;
 
         Ok(LayerShellInputCapture(inner))
     }
 
-    fn add_client(&mut self, pos: Position) {
-        self.0.get_mut().state.add_client(pos);
+    fn add_client(&mut self, edge: Edge) {
+        self.0.get_mut().state.add_client(edge);
     }
 
-    fn delete_client(&mut self, pos: Position) {
-        let inner = self.0.get_mut();
-        inner.state.active_positions.remove(&pos);
-        // remove all windows corresponding to this client
-        while let Some(i) = inner.state.active_windows.iter().position(|w| w.pos == pos) {
-            inner.state.active_windows.remove(i);
-            inner.state.focused = None;
+    fn delete_client(&mut self, edge: Edge) {
+        let state = &mut self.0.get_mut().state;
+        state.active_edges.remove(&edge);
+        state.gate.remove_block(&edge);
+
+        // give the pointer back if it is captured or held against the edge
+        if state.focused.as_ref().is_some_and(|w| w.edge == edge) {
+            state.ungrab();
+            state.focused = None;
+        }
+        if state.at_edge.as_ref().is_some_and(|(w, ..)| w.edge == edge) {
+            state.at_edge = None;
+            if let Some(rel_pointer) = state.rel_pointer.take() {
+                rel_pointer.destroy();
+            }
+        }
+        if let Some((_, offer)) = state.drag.take_if(|(w, _)| w.edge == edge) {
+            state.offers.remove(&offer.id());
+            offer.destroy();
         }
+
+        // remove all windows corresponding to this client
+        state.active_windows.retain(|w| w.edge != edge);
     }
 }
 
 impl State {
     fn update_output_info(&mut self, name: u32) {
-        let output = self
-            .outputs
-            .iter_mut()
-            .find(|o| o.global.name == name)
-            .expect("output not found");
+        let Some(output) = self.outputs.iter_mut().find(|o| o.global.name == name) else {
+            self.errors.push_back(CaptureError::UnknownOutput(name));
+            return;
+        };
         if output.has_xdg_info {
             output.info.replace(output.pending_info.clone());
             self.update_windows();
@@ -400,15 +590,7 @@ impl State {
         });
     }
 
-    fn grab(
-        &mut self,
-        surface: &WlSurface,
-        pointer: &WlPointer,
-        serial: u32,
-        qh: &QueueHandle<State>,
-    ) {
-        let window = self.focused.as_ref().unwrap();
-
+    fn grab(&mut self, window: &Window, pointer: &WlPointer, serial: u32, qh: &QueueHandle<State>) {
         // hide the cursor
         pointer.set_cursor(serial, None, 0, 0);
 
@@ -421,7 +603,7 @@ impl State {
         // lock pointer
         if self.pointer_lock.is_none() {
             self.pointer_lock = Some(self.globals.pointer_constraints.lock_pointer(
-                surface,
+                &window.surface,
                 pointer,
                 None,
                 Lifetime::Persistent,
@@ -440,16 +622,123 @@ impl State {
         }
 
         // capture modifier keys
-        if let Some(shortcut_inhibit_manager) = &self.globals.shortcut_inhibit_manager {
-            if self.shortcut_inhibitor.is_none() {
-                self.shortcut_inhibitor = Some(shortcut_inhibit_manager.inhibit_shortcuts(
-                    surface,
-                    &self.globals.seat,
-                    qh,
-                    (),
-                ));
+        if let Some(shortcut_inhibit_manager) = &self.globals.shortcut_inhibit_manager
+            && self.shortcut_inhibitor.is_none()
+        {
+            self.shortcut_inhibitor = Some(shortcut_inhibit_manager.inhibit_shortcuts(
+                &window.surface,
+                &self.globals.seat,
+                qh,
+                (),
+            ));
+        }
+    }
+
+    // the pointer is over the surface of an edge and pushed `distance` past it,
+    // input is captured once the switch policy allows
+    fn push_edge(&mut self, pointer: &WlPointer, distance: f64, qh: &QueueHandle<State>) {
+        let Some((window, serial, offset)) = self.at_edge.clone() else {
+            return;
+        };
+        let now = Instant::now();
+        if self.gate.in_dead_zone(offset, window.length as f64) {
+            return;
+        }
+        // keys held elsewhere aren't sent to us
+        let fullscreen = self.toplevels.values().any(|&f| f);
+        if self.gate.blocked(
+            &window.edge,
+            !self.buttons.is_empty() || self.dnd,
+            |_| false,
+            fullscreen,
+        ) {
+            return;
+        }
+        if !self.gate.push(distance, now) {
+            // measures the push, the pointer can't move past the edge
+            if self.rel_pointer.is_none() {
+                self.rel_pointer = Some(
+                    self.globals
+                        .relative_pointer_manager
+                        .get_relative_pointer(pointer, qh, ()),
+                );
             }
+            return;
         }
+
+        self.at_edge = None;
+        self.focused = Some(window.clone());
+        self.grab(&window, pointer, serial, qh);
+        self.pending_events
+            .push_back((window.edge.clone(), CaptureEvent::Begin));
+    }
+
+    // asks the source of the files dropped on `window` for their URIs, the
+    // source writes them once our request is flushed so they are read on a thread
+    fn receive_files(&self, window: &Window, offer: &WlDataOffer) -> io::Result<()> {
+        let (mut reader, writer) = io::pipe()?;
+        offer.receive(URI_LIST.to_owned(), writer.as_fd());
+        drop(writer);
+
+        let edge = window.edge.clone();
+        let dropped_tx = self.dropped_tx.clone();
+        thread::spawn(move || {
+            let mut list = String::new();
+            if let Err(e) = reader.read_to_string(&mut list) {
+                eprintln!("failed to read the dropped files: {e}");
+                return;
+            }
+            let paths = parse_uri_list(&list);
+            if !paths.is_empty() {
+                let _ = dropped_tx.send((edge, CaptureEvent::Dropped(paths)));
+            }
+        });
+        Ok(())
+    }
+
+    // takes the keyboard without the pointer entering the surface of `edge`,
+    // the pointer can't be locked unless it's over the surface so it stays local
+    // unless it is captured already
+    fn grab_keyboard(&mut self, edge: &Edge) -> bool {
+        let Some(window) = self
+            .active_windows
+            .iter()
+            .find(|w| &w.edge == edge)
+            .cloned()
+        else {
+            return false;
+        };
+        if self.pointer_lock.is_some() {
+            // switching peers, the pointer stays locked and its motion goes to `edge`
+            if let Some(focused) = self.focused.take() {
+                focused
+                    .layer_surface
+                    .set_keyboard_interactivity(KeyboardInteractivity::None);
+                focused.surface.commit();
+            }
+            if let Some(shortcut_inhibitor) = self.shortcut_inhibitor.take() {
+                shortcut_inhibitor.destroy();
+            }
+        } else {
+            self.ungrab();
+        }
+        window
+            .layer_surface
+            .set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+        window.surface.commit();
+
+        if let Some(shortcut_inhibit_manager) = &self.globals.shortcut_inhibit_manager
+            && self.shortcut_inhibitor.is_none()
+        {
+            self.shortcut_inhibitor = Some(shortcut_inhibit_manager.inhibit_shortcuts(
+                &window.surface,
+                &self.globals.seat,
+                &self.qh,
+                (),
+            ));
+        }
+        self.focused = Some(window);
+        true
     }
 
     fn ungrab(&mut self) {
@@ -484,13 +773,13 @@ impl State {
         }
     }
 
-    fn add_client(&mut self, pos: Position) {
-        self.active_positions.insert(pos);
-        let outputs = get_output_configuration(self, pos);
+    fn add_client(&mut self, edge: Edge) {
+        self.active_edges.insert(edge.clone());
+        let outputs = get_output_configuration(self, &edge);
 
         println!(
-            "adding capture for position {:?} - using outputs: {:?}",
-            pos,
+            "adding capture for edge {:?} - using outputs: {:?}",
+            edge,
             outputs
                 .iter()
                 .map(|o| o
@@ -500,13 +789,14 @@ impl State {
                     .unwrap_or("unknown output".to_owned()))
                 .collect::<Vec<_>>()
         );
-        outputs.iter().for_each(|o| {
+        for o in outputs {
             if let Some(info) = o.info.as_ref() {
-                let window = Window::new(self, &self.qh, &o.wl_output, pos, info.size);
-                let window = Arc::new(window);
-                self.active_windows.push(window);
+                match Window::new(self, &self.qh, &o.wl_output, &edge, info.size) {
+                    Ok(window) => self.active_windows.push(Arc::new(window)),
+                    Err(e) => self.errors.push_back(CaptureError::Surface(e)),
+                }
             }
-        });
+        }
     }
 
     fn update_windows(&mut self) {
@@ -516,37 +806,32 @@ impl State {
         }
 
         self.active_windows.clear();
+        self.at_edge = None;
 
-        let active_positions = self.active_positions.iter().cloned().collect::<Vec<_>>();
-        for pos in active_positions {
-            self.add_client(pos);
+        let active_edges = self.active_edges.iter().cloned().collect::<Vec<_>>();
+        for edge in active_edges {
+            self.add_client(edge);
         }
     }
 }
 
 impl Inner {
-    fn read(&mut self) -> bool {
-        match self.state.read_guard.take().unwrap().read() {
-            Ok(_) => true,
-            Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => false,
-            Err(WaylandError::Io(e)) => {
-                eprintln!("error reading from wayland socket: {e}");
-                false
-            }
-            Err(WaylandError::Protocol(e)) => {
-                panic!("wayland protocol violation: {e}")
-            }
+    fn read(&mut self) -> Result<bool, CaptureError> {
+        let Some(guard) = self.state.read_guard.take() else {
+            return Ok(false);
+        };
+        match guard.read() {
+            Ok(_) => Ok(true),
+            Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(WaylandError::Io(e)) => Err(CaptureError::Io(e)),
+            Err(WaylandError::Protocol(e)) => Err(CaptureError::Protocol(e.to_string())),
         }
     }
 
-    fn prepare_read(&mut self) -> io::Result<()> {
+    fn prepare_read(&mut self) -> Result<(), CaptureError> {
         loop {
             match self.queue.prepare_read() {
-                None => match self.queue.dispatch_pending(&mut self.state) {
-                    Ok(_) => continue,
-                    Err(DispatchError::Backend(WaylandError::Io(e))) => return Err(e),
-                    Err(e) => panic!("failed to dispatch wayland events: {e}"),
-                },
+                None => self.dispatch_events()?,
                 Some(r) => {
                     self.state.read_guard = Some(r);
                     break Ok(());
@@ -555,55 +840,86 @@ impl Inner {
         }
     }
 
-    fn dispatch_events(&mut self) {
+    fn dispatch_events(&mut self) -> Result<(), CaptureError> {
         match self.queue.dispatch_pending(&mut self.state) {
-            Ok(_) => {}
-            Err(DispatchError::Backend(WaylandError::Io(e))) => {
-                eprintln!("Wayland Error: {}", e);
-            }
-            Err(DispatchError::Backend(e)) => {
-                panic!("backend error: {}", e);
+            Ok(_) => Ok(()),
+            Err(DispatchError::Backend(WaylandError::Io(e))) => Err(CaptureError::Io(e)),
+            Err(DispatchError::Backend(WaylandError::Protocol(e))) => {
+                Err(CaptureError::Protocol(e.to_string()))
             }
             Err(DispatchError::BadMessage {
                 sender_id,
                 interface,
                 opcode,
-            }) => {
-                panic!("bad message {}, {} , {}", sender_id, interface, opcode);
-            }
+            }) => Err(CaptureError::Dispatch(format!(
+                "bad message {sender_id}, {interface}, {opcode}"
+            ))),
         }
     }
 
-    fn flush_events(&mut self) -> io::Result<()> {
+    // reads, dispatches and flushes what the socket has for us
+    fn process(&mut self) -> Result<(), CaptureError> {
+        // read events
+        while self.read()? {
+            // prepare next read
+            self.prepare_read()?;
+        }
+
+        // dispatch the events
+        self.dispatch_events()?;
+
+        // flush outgoing events
+        match self.flush_events() {
+            Err(CaptureError::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+            result => result?,
+        }
+
+        // prepare for the next read
+        self.prepare_read()
+    }
+
+    // the stream ends once the connection is lost, after a protocol error
+    // the compositor has disconnected us, an i/o error means the socket is gone
+    fn fail(&mut self, e: CaptureError) -> Report {
+        match &e {
+            CaptureError::Protocol(_) => self.closed = true,
+            CaptureError::Io(e) if e.kind() != ErrorKind::WouldBlock => self.closed = true,
+            _ => {}
+        }
+        e.into()
+    }
+
+    fn flush_events(&mut self) -> Result<(), CaptureError> {
         // flush outgoing events
         match self.queue.flush() {
-            Ok(_) => (),
-            Err(e) => match e {
-                WaylandError::Io(e) => {
-                    return Err(e);
-                }
-                WaylandError::Protocol(e) => {
-                    panic!("wayland protocol violation: {e}")
-                }
-            },
+            Ok(_) => Ok(()),
+            Err(WaylandError::Io(e)) => Err(CaptureError::Io(e)),
+            Err(WaylandError::Protocol(e)) => Err(CaptureError::Protocol(e.to_string())),
         }
-        Ok(())
     }
 }
 
 impl LayerShellInputCapture {
-    pub async fn create(&mut self, pos: Position) -> Result<()> {
-        self.add_client(pos);
+    pub async fn create(&mut self, edge: Edge) -> Result<()> {
+        self.add_client(edge);
         let inner = self.0.get_mut();
         Ok(inner.flush_events()?)
     }
 
-    pub async fn destroy(&mut self, pos: Position) -> Result<()> {
-        self.delete_client(pos);
+    pub async fn destroy(&mut self, edge: Edge) -> Result<()> {
+        self.delete_client(edge);
         let inner = self.0.get_mut();
         Ok(inner.flush_events()?)
     }
 
+    pub async fn grab(&mut self, edge: Edge) -> Result<()> {
+        let inner = self.0.get_mut();
+        if !inner.state.grab_keyboard(&edge) {
+            return Err(Report::msg(format!("no capture window at {edge:?}")));
+        }
+        Ok(inner.flush_events()?)
+    }
+
     pub async fn release(&mut self) -> Result<()> {
         println!("releasing pointer");
         let inner = self.0.get_mut();
@@ -612,15 +928,92 @@ impl LayerShellInputCapture {
     }
 
     pub fn terminate(&mut self) -> Result<()> {
+        let edges: Vec<_> = self
+            .0
+            .get_ref()
+            .state
+            .active_edges
+            .iter()
+            .cloned()
+            .collect();
+        for edge in edges {
+            self.delete_client(edge);
+        }
+        let inner = self.0.get_mut();
+        inner.state.ungrab();
+        Ok(inner.flush_events()?)
+    }
+
+    pub fn set_key_mode(&mut self, mode: KeyMode) {
+        self.0.get_mut().state.key_mode = mode;
+    }
+
+    pub fn set_switch_policy(&mut self, policy: SwitchPolicy) {
+        self.0.get_mut().state.gate.set_policy(policy);
+    }
+
+    pub fn set_switch_block(&mut self, edge: Edge, block: SwitchBlock) -> Result<()> {
+        let state = &mut self.0.get_mut().state;
+        if !block.keys.is_empty() {
+            return Err(Report::msg("held keys can't block switching on wayland"));
+        }
+        if block.fullscreen && state.globals.toplevel_manager.is_none() {
+            println!("fullscreen windows can't block switching without foreign_toplevel_manager");
+        }
+        state.gate.set_block(edge, block);
         Ok(())
     }
+
+    pub fn keymap(&self) -> Option<Keymap> {
+        let state = &self.0.get_ref().state;
+        state.keymap.clone().map(|xkb| Keymap {
+            xkb,
+            group: state.group,
+        })
+    }
+
+    pub fn locks(&self) -> Option<u32> {
+        self.0.get_ref().state.locked
+    }
+
+    pub fn repeat_info(&self) -> Option<RepeatInfo> {
+        self.0.get_ref().state.repeat_info
+    }
+
+    pub fn screens(&self) -> Vec<Screen> {
+        self.0
+            .get_ref()
+            .state
+            .outputs
+            .iter()
+            .filter_map(|output| output.info.as_ref())
+            .map(|info| Screen {
+                name: Some(info.name.clone()),
+                x: info.position.0,
+                y: info.position.1,
+                width: info.size.0,
+                height: info.size.1,
+            })
+            .collect()
+    }
 }
 
 impl Stream for LayerShellInputCapture {
-    type Item = Result<(Position, CaptureEvent)>;
+    type Item = Result<(Edge, CaptureEvent)>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if let Some(event) = self.0.get_mut().state.pending_events.pop_front() {
+        let inner = self.0.get_mut();
+        if inner.closed {
+            return Poll::Ready(None);
+        }
+        if let Some(e) = inner.state.errors.pop_front() {
+            return Poll::Ready(Some(Err(e.into())));
+        }
+        if let Some(event) = inner.state.pending_events.pop_front() {
+            return Poll::Ready(Some(Ok(event)));
+        }
+
+        if let Poll::Ready(Some(event)) = inner.state.dropped_rx.poll_recv(cx) {
             return Poll::Ready(Some(Ok(event)));
         }
 
@@ -629,30 +1022,8 @@ impl Stream for LayerShellInputCapture {
 
             {
                 let inner = guard.get_inner_mut();
-
-                // read events
-                while inner.read() {
-                    // prepare next read
-                    match inner.prepare_read() {
-                        Ok(_) => {}
-                        Err(e) => return Poll::Ready(Some(Err(e.into()))),
-                    }
-                }
-
-                // dispatch the events
-                inner.dispatch_events();
-
-                // flush outgoing events
-                if let Err(e) = inner.flush_events() {
-                    if e.kind() != ErrorKind::WouldBlock {
-                        return Poll::Ready(Some(Err(e.into())));
-                    }
-                }
-
-                // prepare for the next read
-                match inner.prepare_read() {
-                    Ok(_) => {}
-                    Err(e) => return Poll::Ready(Some(Err(e.into()))),
+                if let Err(e) = inner.process() {
+                    return Poll::Ready(Some(Err(inner.fail(e))));
                 }
             }
 
@@ -661,9 +1032,12 @@ impl Stream for LayerShellInputCapture {
             guard.clear_ready();
 
             // if an event has been queued during dispatch_events() we return it
-            match guard.get_inner_mut().state.pending_events.pop_front() {
-                Some(event) => return Poll::Ready(Some(Ok(event))),
-                None => continue,
+            let state = &mut guard.get_inner_mut().state;
+            if let Some(e) = state.errors.pop_front() {
+                return Poll::Ready(Some(Err(e.into())));
+            }
+            if let Some(event) = state.pending_events.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
             }
         }
     }
@@ -711,25 +1085,32 @@ impl Dispatch<WlPointer, ()> for State {
             wl_pointer::Event::Enter {
                 serial,
                 surface,
-                surface_x: _,
-                surface_y: _,
+                surface_x,
+                surface_y,
             } => {
                 // get client corresponding to the focused surface
-                {
-                    if let Some(window) = app.active_windows.iter().find(|w| w.surface == surface) {
-                        app.focused = Some(window.clone());
-                        app.grab(&surface, pointer, serial, qh);
-                    } else {
-                        return;
-                    }
-                }
-                let pos = app
+                let Some(window) = app
                     .active_windows
                     .iter()
                     .find(|w| w.surface == surface)
-                    .map(|w| w.pos)
-                    .unwrap();
-                app.pending_events.push_back((pos, CaptureEvent::Begin));
+                    .cloned()
+                else {
+                    return;
+                };
+                let offset = window.offset_along(surface_x, surface_y);
+                app.at_edge = Some((window, serial, offset));
+                app.push_edge(pointer, 0., qh);
+            }
+            wl_pointer::Event::Motion {
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                // moving along the edge, possibly into a corner
+                if let Some((window, _, offset)) = app.at_edge.as_mut() {
+                    *offset = window.offset_along(surface_x, surface_y);
+                }
+                app.push_edge(pointer, 0., qh);
             }
             wl_pointer::Event::Leave { .. } => {
                 /* There are rare cases, where when a window is opened in
@@ -743,6 +1124,15 @@ impl Dispatch<WlPointer, ()> for State {
                     println!("compositor released mouse");
                 }
                 app.ungrab();
+
+                // left the edge without switching
+                app.buttons.clear();
+                if app.at_edge.take().is_some() {
+                    app.gate.leave(Instant::now());
+                    if let Some(rel_pointer) = app.rel_pointer.take() {
+                        rel_pointer.destroy();
+                    }
+                }
             }
             wl_pointer::Event::Button {
                 serial: _,
@@ -750,9 +1140,18 @@ impl Dispatch<WlPointer, ()> for State {
                 button,
                 state,
             } => {
-                let window = app.focused.as_ref().unwrap();
+                let Some(window) = app.focused.as_ref() else {
+                    // over the edge, before switching
+                    match state {
+                        WEnum::Value(wl_pointer::ButtonState::Pressed) => {
+                            app.buttons.insert(button)
+                        }
+                        _ => app.buttons.remove(&button),
+                    };
+                    return;
+                };
                 app.pending_events.push_back((
-                    window.pos,
+                    window.edge.clone(),
                     CaptureEvent::Input(Event::Pointer(PointerEvent::Button {
                         time,
                         button,
@@ -761,7 +1160,9 @@ impl Dispatch<WlPointer, ()> for State {
                 ));
             }
             wl_pointer::Event::Axis { time, axis, value } => {
-                let window = app.focused.as_ref().unwrap();
+                let Some(window) = app.focused.as_ref() else {
+                    return;
+                };
                 if app.scroll_discrete_pending {
                     // each axisvalue120 event is coupled with
                     // a corresponding axis event, which needs to
@@ -769,7 +1170,7 @@ impl Dispatch<WlPointer, ()> for State {
                     app.scroll_discrete_pending = false;
                 } else {
                     app.pending_events.push_back((
-                        window.pos,
+                        window.edge.clone(),
                         CaptureEvent::Input(Event::Pointer(PointerEvent::Axis {
                             time,
                             axis: u32::from(axis) as u8,
@@ -779,20 +1180,54 @@ impl Dispatch<WlPointer, ()> for State {
                 }
             }
             wl_pointer::Event::AxisValue120 { axis, value120 } => {
-                let window = app.focused.as_ref().unwrap();
+                let Some(window) = app.focused.as_ref() else {
+                    return;
+                };
                 app.scroll_discrete_pending = true;
                 app.pending_events.push_back((
-                    window.pos,
+                    window.edge.clone(),
                     CaptureEvent::Input(Event::Pointer(PointerEvent::AxisDiscrete120 {
                         axis: u32::from(axis) as u8,
                         value: value120,
                     })),
                 ));
             }
+            wl_pointer::Event::AxisSource {
+                axis_source: WEnum::Value(axis_source),
+            } => {
+                let Some(window) = app.focused.as_ref() else {
+                    return;
+                };
+                let source = match axis_source {
+                    wl_pointer::AxisSource::Finger => AxisSource::Finger,
+                    wl_pointer::AxisSource::Continuous => AxisSource::Continuous,
+                    wl_pointer::AxisSource::WheelTilt => AxisSource::WheelTilt,
+                    _ => AxisSource::Wheel,
+                };
+                app.pending_events.push_back((
+                    window.edge.clone(),
+                    CaptureEvent::Input(Event::Pointer(PointerEvent::AxisSource { source })),
+                ));
+            }
+            wl_pointer::Event::AxisStop { time, axis } => {
+                let Some(window) = app.focused.as_ref() else {
+                    return;
+                };
+                app.pending_events.push_back((
+                    window.edge.clone(),
+                    CaptureEvent::Input(Event::Pointer(PointerEvent::AxisStop {
+                        time,
+                        axis: u32::from(axis) as u8,
+                    })),
+                ));
+            }
             wl_pointer::Event::Frame => {
-                // TODO properly handle frame events
-                // we simply insert a frame event on the client side
-                // after each event for now
+                if let Some(window) = app.focused.as_ref() {
+                    app.pending_events.push_back((
+                        window.edge.clone(),
+                        CaptureEvent::Input(Event::Pointer(PointerEvent::Frame)),
+                    ));
+                }
             }
             _ => {}
         }
@@ -816,15 +1251,19 @@ impl Dispatch<WlKeyboard, ()> for State {
                 key,
                 state,
             } => {
-                if let Some(window) = window {
-                    app.pending_events.push_back((
-                        window.pos,
-                        CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key {
+                if let Some(edge) = window.as_ref().map(|w| w.edge.clone()) {
+                    let state = u32::from(state) as u8;
+                    let event = match app.translate_key(key, state) {
+                        Some(keysym) => KeyboardEvent::Keysym {
                             time,
                             key,
-                            state: u32::from(state) as u8,
-                        })),
-                    ));
+                            keysym,
+                            state,
+                        },
+                        None => KeyboardEvent::Key { time, key, state },
+                    };
+                    app.pending_events
+                        .push_back((edge, CaptureEvent::Input(Event::Keyboard(event))));
                 }
             }
             wl_keyboard::Event::Modifiers {
@@ -834,9 +1273,14 @@ impl Dispatch<WlKeyboard, ()> for State {
                 mods_locked,
                 group,
             } => {
+                app.group = group;
+                app.locked = Some(mods_locked);
+                if let Some(xkb) = app.xkb.as_mut() {
+                    xkb.update_mask(mods_depressed, mods_latched, mods_locked, group);
+                }
                 if let Some(window) = window {
                     app.pending_events.push_back((
-                        window.pos,
+                        window.edge.clone(),
                         CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Modifiers {
                             depressed: mods_depressed,
                             latched: mods_latched,
@@ -846,11 +1290,65 @@ impl Dispatch<WlKeyboard, ()> for State {
                     ));
                 }
             }
+            wl_keyboard::Event::Keymap {
+                format: WEnum::Value(wl_keyboard::KeymapFormat::XkbV1),
+                fd,
+                size,
+            } => match read_keymap(fd, size) {
+                Ok(keymap) => {
+                    app.xkb = xkb::Keymap::new(&keymap).and_then(|k| xkb::State::new(&k));
+                    if app.xkb.is_none() {
+                        eprintln!("failed to compile keymap");
+                    }
+                    app.keymap = Some(keymap);
+                }
+                Err(e) => eprintln!("failed to read keymap: {e}"),
+            },
+            // the locks can change without us being told until the next focus
+            wl_keyboard::Event::Leave { .. } => app.locked = None,
+            wl_keyboard::Event::RepeatInfo { rate, delay } => {
+                app.repeat_info = Some(RepeatInfo {
+                    rate: rate.max(0) as u32,
+                    delay: delay.max(0) as u32,
+                });
+            }
             _ => (),
         }
     }
 }
 
+impl State {
+    // the keysym to send instead of `key` in keysym mode
+    fn translate_key(&mut self, key: u32, state: u8) -> Option<u32> {
+        if state == 0 {
+            // released as it was pressed, whatever the modifiers are now
+            return self.pressed_keysyms.remove(&key);
+        }
+        if self.key_mode != KeyMode::Keysym {
+            return None;
+        }
+
+        let c = self.xkb.as_ref()?.key_char(key + 8)?;
+        if !keysym::is_printable(c) {
+            return None;
+        }
+        let keysym = keysym::from_char(c);
+        self.pressed_keysyms.insert(key, keysym);
+        Some(keysym)
+    }
+}
+
+fn read_keymap(fd: OwnedFd, size: u32) -> io::Result<String> {
+    // the file offset may be shared with other clients, don't move it
+    let mut buf = vec![0; size as usize];
+    File::from(fd).read_exact_at(&mut buf, 0)?;
+
+    // the keymap is null terminated
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
 impl Dispatch<ZwpRelativePointerV1, ()> for State {
     fn event(
         app: &mut Self,
@@ -858,7 +1356,7 @@ impl Dispatch<ZwpRelativePointerV1, ()> for State {
         event: <ZwpRelativePointerV1 as wayland_client::Proxy>::Event,
         _: &(),
         _: &Connection,
-        _: &QueueHandle<Self>,
+        qh: &QueueHandle<Self>,
     ) {
         if let zwp_relative_pointer_v1::Event::RelativeMotion {
             utime_hi,
@@ -871,14 +1369,167 @@ impl Dispatch<ZwpRelativePointerV1, ()> for State {
             if let Some(window) = &app.focused {
                 let time = ((((utime_hi as u64) << 32) | utime_lo as u64) / 1000) as u32;
                 app.pending_events.push_back((
-                    window.pos,
+                    window.edge.clone(),
                     CaptureEvent::Input(Event::Pointer(PointerEvent::Motion { time, dx, dy })),
                 ));
+            } else if let Some((window, ..)) = &app.at_edge
+                && let Some(pointer) = app.pointer.clone()
+            {
+                let distance = window.push_distance(dx, dy);
+                app.push_edge(&pointer, distance, qh);
             }
         }
     }
 }
 
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        app: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: <ZwlrForeignToplevelManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            app.toplevels.insert(toplevel.id(), false);
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        app: &mut Self,
+        toplevel: &ZwlrForeignToplevelHandleV1,
+        event: <ZwlrForeignToplevelHandleV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                // an array of native endian u32
+                let states: Vec<u32> = state
+                    .chunks_exact(4)
+                    .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                let has = |s: zwlr_foreign_toplevel_handle_v1::State| states.contains(&(s as u32));
+                let fullscreen = has(zwlr_foreign_toplevel_handle_v1::State::Activated)
+                    && has(zwlr_foreign_toplevel_handle_v1::State::Fullscreen);
+                app.toplevels.insert(toplevel.id(), fullscreen);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                app.toplevels.remove(&toplevel.id());
+                toplevel.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlDataDevice, ()> for State {
+    fn event(
+        app: &mut Self,
+        _: &WlDataDevice,
+        event: <WlDataDevice as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_device::Event::DataOffer { id } => {
+                app.offers.insert(id.id(), vec![]);
+            }
+            wl_data_device::Event::Enter {
+                serial,
+                surface,
+                id,
+                ..
+            } => {
+                let window = app.active_windows.iter().find(|w| w.surface == surface);
+                app.dnd = window.is_some();
+                let Some(offer) = id else {
+                    return;
+                };
+                let files = app
+                    .offers
+                    .get(&offer.id())
+                    .is_some_and(|offered| offered.iter().any(|m| m == URI_LIST));
+                match window {
+                    Some(window) if files => {
+                        offer.accept(serial, Some(URI_LIST.to_owned()));
+                        offer.set_actions(DndAction::Copy, DndAction::Copy);
+                        app.drag = Some((window.clone(), offer));
+                        app.drag_copy = false;
+                    }
+                    _ => offer.accept(serial, None),
+                }
+            }
+            wl_data_device::Event::Leave => {
+                app.dnd = false;
+                if let Some((_, offer)) = app.drag.take() {
+                    app.offers.remove(&offer.id());
+                    offer.destroy();
+                }
+            }
+            wl_data_device::Event::Drop => {
+                app.dnd = false;
+                let Some((window, offer)) = app.drag.take() else {
+                    return;
+                };
+                app.offers.remove(&offer.id());
+                if let Err(e) = app.receive_files(&window, &offer) {
+                    eprintln!("failed to receive the dropped files: {e}");
+                }
+                // only a copy can be finished, the source cancels otherwise
+                if app.drag_copy {
+                    offer.finish();
+                }
+                offer.destroy();
+            }
+            wl_data_device::Event::Selection { id: Some(offer) } => {
+                // the clipboard is left to okbm-clipboard
+                app.offers.remove(&offer.id());
+                offer.destroy();
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, WlDataDevice, [
+        wl_data_device::EVT_DATA_OFFER_OPCODE => (WlDataOffer, ()),
+    ]);
+}
+
+impl Dispatch<WlDataOffer, ()> for State {
+    fn event(
+        app: &mut Self,
+        offer: &WlDataOffer,
+        event: <WlDataOffer as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_offer::Event::Offer { mime_type } => {
+                if let Some(offered) = app.offers.get_mut(&offer.id()) {
+                    offered.push(mime_type);
+                }
+            }
+            wl_data_offer::Event::Action {
+                dnd_action: WEnum::Value(action),
+            } if app.drag.as_ref().is_some_and(|(_, o)| o == offer) => {
+                app.drag_copy = action == DndAction::Copy;
+            }
+            _ => {}
+        }
+    }
+}
+
 impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
     fn event(
         app: &mut Self,
@@ -888,19 +1539,18 @@ impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
-        if let zwlr_layer_surface_v1::Event::Configure { serial, .. } = event {
-            if let Some(window) = app
+        if let zwlr_layer_surface_v1::Event::Configure { serial, .. } = event
+            && let Some(window) = app
                 .active_windows
                 .iter()
                 .find(|w| &w.layer_surface == layer_surface)
-            {
-                // client corresponding to the layer_surface
-                let surface = &window.surface;
-                let buffer = &window.buffer;
-                surface.attach(Some(buffer), 0, 0);
-                layer_surface.ack_configure(serial);
-                surface.commit();
-            }
+        {
+            // client corresponding to the layer_surface
+            let surface = &window.surface;
+            let buffer = &window.buffer;
+            surface.attach(Some(buffer), 0, 0);
+            layer_surface.ack_configure(serial);
+            surface.commit();
         }
     }
 }
@@ -944,11 +1594,10 @@ impl Dispatch<ZxdgOutputV1, u32> for State {
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
-        let output = state
-            .outputs
-            .iter_mut()
-            .find(|o| o.global.name == *name)
-            .expect("output");
+        let Some(output) = state.outputs.iter_mut().find(|o| o.global.name == *name) else {
+            state.errors.push_back(CaptureError::UnknownOutput(*name));
+            return;
+        };
 
         println!("xdg_output {name} - {:?}", event);
         match event {
@@ -972,7 +1621,9 @@ impl Dispatch<ZxdgOutputV1, u32> for State {
                 output.pending_info.description = description;
                 output.has_xdg_info = true;
             }
-            _ => todo!(),
+            event => state.errors.push_back(CaptureError::Dispatch(format!(
+                "unexpected xdg_output event {event:?}"
+            ))),
         }
     }
 }
@@ -1001,6 +1652,7 @@ delegate_noop!(State: ZwlrLayerShellV1);
 delegate_noop!(State: ZwpRelativePointerManagerV1);
 delegate_noop!(State: ZwpKeyboardShortcutsInhibitManagerV1);
 delegate_noop!(State: ZwpPointerConstraintsV1);
+delegate_noop!(State: WlDataDeviceManager);
 
 // ignore events
 delegate_noop!(State: ignore ZxdgOutputManagerV1);
@@ -1009,3 +1661,34 @@ delegate_noop!(State: ignore wl_buffer::WlBuffer);
 delegate_noop!(State: ignore WlSurface);
 delegate_noop!(State: ignore ZwpKeyboardShortcutsInhibitorV1);
 delegate_noop!(State: ignore ZwpLockedPointerV1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_lists() {
+        let list = "# dropped from a file manager\r\n\
+                    file:///home/me/a%20b.txt\r\n\
+                    file://host/tmp/c.png\r\n\
+                    https://example.com/d\r\n";
+        assert_eq!(
+            parse_uri_list(list),
+            [
+                PathBuf::from("/home/me/a b.txt"),
+                PathBuf::from("/tmp/c.png")
+            ]
+        );
+        assert!(parse_uri_list("").is_empty());
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("/a%2Fb%c3%a9").as_deref(), Some("/a/bé"));
+        // a trailing % without two digits is kept
+        assert_eq!(percent_decode("100%").as_deref(), Some("100%"));
+        assert_eq!(percent_decode("%zz"), None);
+        // not UTF-8
+        assert_eq!(percent_decode("%ff"), None);
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+pub(crate) use macos::*;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod wayland;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) use wayland::*;
+
+use serde::{Deserialize, Serialize};
+
+pub use eyre::Result;
+
+/// MIME types shared with peers, backends map the other names of text to the first one.
+pub const MIME_TYPES: [&str; 4] = [
+    "text/plain;charset=utf-8",
+    "text/html",
+    "text/uri-list",
+    "image/png",
+];
+
+/// The regular clipboard, or the primary selection pasted with the middle
+/// button, which only Wayland has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+}
+
+/// Data of the clipboard for each MIME type it offers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardContent {
+    pub offers: Vec<(String, Vec<u8>)>,
+}
+
+impl ClipboardContent {
+    pub fn size(&self) -> usize {
+        self.offers.iter().map(|(_, data)| data.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offers.is_empty()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum ClipboardKind {
+    #[cfg(target_os = "macos")]
+    MacOS(MacOSClipboard),
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Wayland(WaylandClipboard),
+}
+
+impl ClipboardKind {
+    pub fn generation(&self, selection: Selection) -> u64 {
+        match self {
+            #[cfg(target_os = "macos")]
+            ClipboardKind::MacOS(clipboard) => match selection {
+                Selection::Clipboard => clipboard.generation(),
+                Selection::Primary => 0,
+            },
+            #[cfg(all(unix, not(target_os = "macos")))]
+            ClipboardKind::Wayland(clipboard) => clipboard.generation(selection),
+        }
+    }
+
+    pub async fn read(&self, selection: Selection, max_size: usize) -> Result<ClipboardContent> {
+        match self {
+            #[cfg(target_os = "macos")]
+            ClipboardKind::MacOS(clipboard) => match selection {
+                Selection::Clipboard => clipboard.read(max_size),
+                Selection::Primary => Ok(ClipboardContent::default()),
+            },
+            #[cfg(all(unix, not(target_os = "macos")))]
+            ClipboardKind::Wayland(clipboard) => clipboard.read(selection, max_size).await,
+        }
+    }
+
+    pub fn write(&mut self, selection: Selection, content: ClipboardContent) -> Result<u64> {
+        match self {
+            #[cfg(target_os = "macos")]
+            ClipboardKind::MacOS(clipboard) => match selection {
+                Selection::Clipboard => clipboard.write(content),
+                Selection::Primary => Ok(0),
+            },
+            #[cfg(all(unix, not(target_os = "macos")))]
+            ClipboardKind::Wayland(clipboard) => clipboard.write(selection, content),
+        }
+    }
+}
+
+/// The local clipboard, read when control goes to a peer and written with
+/// what a peer sends when it comes back. Clones share the connection,
+/// e.g. to read in a task.
+#[derive(Clone)]
+pub struct Clipboard {
+    clipboard: ClipboardKind,
+}
+
+impl Clipboard {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            clipboard: {
+                #[cfg(target_os = "macos")]
+                let clipboard = ClipboardKind::MacOS(MacOSClipboard::new());
+
+                #[cfg(all(unix, not(target_os = "macos")))]
+                let clipboard = ClipboardKind::Wayland(WaylandClipboard::new()?);
+
+                clipboard
+            },
+        })
+    }
+
+    /// Changes whenever the content of `selection` does.
+    pub fn generation(&self, selection: Selection) -> u64 {
+        self.clipboard.generation(selection)
+    }
+
+    /// The content of `selection`, leaving out the MIME types that don't
+    /// fit in `max_size` bytes in total. Empty without a primary selection.
+    pub async fn read(&self, selection: Selection, max_size: usize) -> Result<ClipboardContent> {
+        self.clipboard.read(selection, max_size).await
+    }
+
+    /// Takes over `selection` to offer `content`, returns the generation it has then.
+    pub fn write(&mut self, selection: Selection, content: ClipboardContent) -> Result<u64> {
+        self.clipboard.write(selection, content)
+    }
+}
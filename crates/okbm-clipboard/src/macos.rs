@@ -0,0 +1,83 @@
+use okbm_common::objc::*;
+use std::{ffi::CStr, slice};
+
+use crate::*;
+
+// pasteboard types of the shared MIME types
+const TYPES: [(&str, &CStr); 4] = [
+    ("text/plain;charset=utf-8", c"public.utf8-plain-text"),
+    ("text/html", c"public.html"),
+    ("text/uri-list", c"public.url"),
+    ("image/png", c"public.png"),
+];
+
+unsafe fn pasteboard() -> Id {
+    unsafe { send(class(c"NSPasteboard"), c"generalPasteboard") }
+}
+
+#[derive(Clone)]
+pub(crate) struct MacOSClipboard;
+
+impl MacOSClipboard {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn generation(&self) -> u64 {
+        unsafe { send::<isize>(pasteboard(), c"changeCount") as u64 }
+    }
+
+    pub fn read(&self, max_size: usize) -> Result<ClipboardContent> {
+        let mut content = ClipboardContent::default();
+        unsafe {
+            let pool = objc_autoreleasePoolPush();
+            let pasteboard = pasteboard();
+            for (mime, kind) in TYPES {
+                let data: Id = send1(pasteboard, c"dataForType:", ns_string(kind));
+                if data.is_null() {
+                    continue;
+                }
+                let len: usize = send(data, c"length");
+                if len == 0 {
+                    continue;
+                }
+                if content.size() + len > max_size {
+                    println!("{mime} clipboard data over the size limit, skipped");
+                    continue;
+                }
+                let bytes: *const u8 = send(data, c"bytes");
+                content
+                    .offers
+                    .push((mime.to_owned(), slice::from_raw_parts(bytes, len).to_vec()));
+            }
+            objc_autoreleasePoolPop(pool);
+        }
+        Ok(content)
+    }
+
+    pub fn write(&mut self, content: ClipboardContent) -> Result<u64> {
+        unsafe {
+            let pool = objc_autoreleasePoolPush();
+            let pasteboard = pasteboard();
+            send::<isize>(pasteboard, c"clearContents");
+            for (mime, data) in &content.offers {
+                let Some((_, kind)) = TYPES.iter().find(|(m, _)| m == mime) else {
+                    continue;
+                };
+                let data: Id = send2(
+                    class(c"NSData"),
+                    c"dataWithBytes:length:",
+                    data.as_ptr(),
+                    data.len(),
+                );
+                // BOOL, whether the pasteboard took it
+                if send2::<Id, Id, i8>(pasteboard, c"setData:forType:", data, ns_string(kind)) == 0
+                {
+                    eprintln!("failed to write {mime} to the clipboard");
+                }
+            }
+            objc_autoreleasePoolPop(pool);
+        }
+        Ok(self.generation())
+    }
+}
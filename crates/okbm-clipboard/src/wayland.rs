@@ -0,0 +1,318 @@
+use eyre::Report;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Write},
+    mem,
+    os::fd::{AsFd, OwnedFd},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tokio::{io::AsyncReadExt, net::unix::pipe};
+
+use wayland_client::{
+    Connection, Dispatch, Proxy, QueueHandle,
+    backend::ObjectId,
+    delegate_noop, event_created_child,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::{wl_registry::WlRegistry, wl_seat::WlSeat},
+};
+
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+    zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
+};
+
+use crate::*;
+
+const TEXT: &str = MIME_TYPES[0];
+// other names text is offered under
+const TEXT_ALIASES: [&str; 4] = ["UTF8_STRING", "text/plain", "TEXT", "STRING"];
+
+// time a client has to send us its clipboard
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct Offered {
+    // offer of the current selection and its MIME types
+    offer: Option<(ZwlrDataControlOfferV1, Vec<String>)>,
+    generation: u64,
+    // the next selection is the one we wrote, `write` counted it already
+    written: bool,
+}
+
+#[derive(Default)]
+struct Selections {
+    clipboard: Offered,
+    primary: Offered,
+}
+
+impl Selections {
+    fn get(&mut self, selection: Selection) -> &mut Offered {
+        match selection {
+            Selection::Clipboard => &mut self.clipboard,
+            Selection::Primary => &mut self.primary,
+        }
+    }
+}
+
+struct State {
+    // MIME types of the offers not yet made a selection
+    offers: HashMap<ObjectId, Vec<String>>,
+    selections: Arc<Mutex<Selections>>,
+}
+
+impl State {
+    fn select(&mut self, selection: Selection, offer: Option<ZwlrDataControlOfferV1>) {
+        let offer = offer.map(|offer| {
+            let offered = self.offers.remove(&offer.id()).unwrap_or_default();
+            (offer, offered)
+        });
+        if let Ok(mut selections) = self.selections.lock() {
+            let current = selections.get(selection);
+            if let Some((previous, _)) = current.offer.take() {
+                previous.destroy();
+            }
+            current.offer = offer;
+            if !mem::take(&mut current.written) {
+                current.generation += 1;
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct WaylandClipboard {
+    conn: Connection,
+    qh: QueueHandle<State>,
+    manager: ZwlrDataControlManagerV1,
+    device: ZwlrDataControlDeviceV1,
+    selections: Arc<Mutex<Selections>>,
+}
+
+impl WaylandClipboard {
+    pub fn new() -> Result<Self> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+
+        // the primary selection comes with version 2
+        let manager: ZwlrDataControlManagerV1 = globals.bind(&qh, 1..=2, ())?;
+        let seat: WlSeat = globals.bind(&qh, 1..=8, ())?;
+        let device = manager.get_data_device(&seat, &qh, ());
+
+        let selections = Arc::new(Mutex::new(Selections::default()));
+        let mut state = State {
+            offers: HashMap::new(),
+            selections: selections.clone(),
+        };
+
+        // other clients may ask for what we offer at any time
+        thread::spawn(move || {
+            loop {
+                if let Err(e) = queue.blocking_dispatch(&mut state) {
+                    eprintln!("clipboard connection lost: {e}");
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            conn,
+            qh,
+            manager,
+            device,
+            selections,
+        })
+    }
+
+    pub fn generation(&self, selection: Selection) -> u64 {
+        self.selections
+            .lock()
+            .map(|mut selections| selections.get(selection).generation)
+            .unwrap_or_default()
+    }
+
+    pub async fn read(&self, selection: Selection, max_size: usize) -> Result<ClipboardContent> {
+        let offer = self
+            .selections
+            .lock()
+            .map_err(|_| Report::msg("clipboard state poisoned"))?
+            .get(selection)
+            .offer
+            .clone();
+        let Some((offer, offered)) = offer else {
+            return Ok(ClipboardContent::default());
+        };
+
+        let mut content = ClipboardContent::default();
+        for mime in MIME_TYPES {
+            let names = match mime {
+                TEXT => [TEXT].iter().chain(TEXT_ALIASES.iter()).copied().collect(),
+                mime => vec![mime],
+            };
+            let Some(name) = names.into_iter().find(|n| offered.iter().any(|o| o == n)) else {
+                continue;
+            };
+
+            let budget = max_size.saturating_sub(content.size());
+            let (reader, writer) = io::pipe()?;
+            offer.receive(name.to_owned(), writer.as_fd());
+            self.conn.flush()?;
+            drop(writer);
+
+            // a source that never closes its end is given up on, dropping ours
+            let reader = pipe::Receiver::from_owned_fd(OwnedFd::from(reader))?;
+            let mut data = vec![];
+            let read = tokio::time::timeout(
+                READ_TIMEOUT,
+                reader.take(budget as u64 + 1).read_to_end(&mut data),
+            )
+            .await;
+            match read {
+                Ok(Ok(_)) if data.len() <= budget => content.offers.push((mime.to_owned(), data)),
+                Ok(Ok(_)) => println!("{mime} clipboard data over the size limit, skipped"),
+                Ok(Err(e)) => eprintln!("failed to read {mime} from the clipboard: {e}"),
+                Err(_) => eprintln!("timed out reading {mime} from the clipboard"),
+            }
+        }
+        Ok(content)
+    }
+
+    pub fn write(&mut self, selection: Selection, content: ClipboardContent) -> Result<u64> {
+        if selection == Selection::Primary && self.device.version() < 2 {
+            return Err(Report::msg(
+                "the compositor's data-control has no primary selection",
+            ));
+        }
+
+        // counted now, before the compositor tells us about our own selection
+        let generation = {
+            let mut selections = self
+                .selections
+                .lock()
+                .map_err(|_| Report::msg("clipboard state poisoned"))?;
+            let current = selections.get(selection);
+            current.generation += 1;
+            current.written = true;
+            current.generation
+        };
+
+        let mut names = vec![];
+        for (mime, _) in &content.offers {
+            names.push(mime.clone());
+            if mime == TEXT {
+                names.extend(TEXT_ALIASES.iter().map(|&alias| alias.to_owned()));
+            }
+        }
+
+        let source = self.manager.create_data_source(&self.qh, Arc::new(content));
+        for name in names {
+            source.offer(name);
+        }
+        match selection {
+            Selection::Clipboard => self.device.set_selection(Some(&source)),
+            Selection::Primary => self.device.set_primary_selection(Some(&source)),
+        }
+        self.conn.flush()?;
+        Ok(generation)
+    }
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlDeviceV1,
+        event: <ZwlrDataControlDeviceV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::DataOffer { id } => {
+                state.offers.insert(id.id(), vec![]);
+            }
+            zwlr_data_control_device_v1::Event::Selection { id } => {
+                state.select(Selection::Clipboard, id);
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id } => {
+                state.select(Selection::Primary, id);
+            }
+            zwlr_data_control_device_v1::Event::Finished => {
+                eprintln!("clipboard data device no longer valid");
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        offer: &ZwlrDataControlOfferV1,
+        event: <ZwlrDataControlOfferV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event
+            && let Some(offered) = state.offers.get_mut(&offer.id())
+        {
+            offered.push(mime_type);
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlSourceV1, Arc<ClipboardContent>> for State {
+    fn event(
+        _: &mut Self,
+        source: &ZwlrDataControlSourceV1,
+        event: <ZwlrDataControlSourceV1 as Proxy>::Event,
+        content: &Arc<ClipboardContent>,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_source_v1::Event::Send { mime_type, fd } => {
+                let mime = match TEXT_ALIASES.contains(&mime_type.as_str()) {
+                    true => TEXT,
+                    false => mime_type.as_str(),
+                };
+                let Some((_, data)) = content.offers.iter().find(|(m, _)| m == mime) else {
+                    return;
+                };
+                // a slow reader must not hold up the connection
+                let data = data.clone();
+                thread::spawn(move || {
+                    if let Err(e) = File::from(fd).write_all(&data) {
+                        eprintln!("failed to send {mime_type} from the clipboard: {e}");
+                    }
+                });
+            }
+            zwlr_data_control_source_v1::Event::Cancelled => source.destroy(),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(State: ZwlrDataControlManagerV1);
+delegate_noop!(State: ignore WlSeat);
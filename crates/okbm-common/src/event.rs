@@ -1,10 +1,36 @@
 use serde::{Deserialize, Serialize};
 
+// evdev button codes, see linux/input-event-codes.h
 pub const BTN_LEFT: u32 = 0x110;
 pub const BTN_RIGHT: u32 = 0x111;
 pub const BTN_MIDDLE: u32 = 0x112;
-pub const BTN_BACK: u32 = 0x113;
-pub const BTN_FORWARD: u32 = 0x114;
+pub const BTN_SIDE: u32 = 0x113;
+pub const BTN_EXTRA: u32 = 0x114;
+pub const BTN_FORWARD: u32 = 0x115;
+pub const BTN_BACK: u32 = 0x116;
+pub const BTN_TASK: u32 = 0x117;
+
+/// Mouse buttons indexed by the button number used by macOS, where 3 and 4
+/// are the thumb buttons, side and extra on Linux.
+pub const BUTTONS: [u32; 8] = [
+    BTN_LEFT,
+    BTN_RIGHT,
+    BTN_MIDDLE,
+    BTN_SIDE,
+    BTN_EXTRA,
+    BTN_FORWARD,
+    BTN_BACK,
+    BTN_TASK,
+];
+
+// mirrors wl_pointer::axis_source
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum AxisSource {
+    Wheel,
+    Finger,
+    Continuous,
+    WheelTilt,
+}
 
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum PointerEvent {
@@ -15,6 +41,15 @@ pub enum PointerEvent {
     Axis { time: u32, axis: u8, value: f64 },
 
     AxisDiscrete120 { axis: u8, value: i32 },
+
+    // source of the axis events of the current frame
+    AxisSource { source: AxisSource },
+
+    // scrolling on `axis` stopped, e.g. fingers lifted from a touchpad
+    AxisStop { time: u32, axis: u8 },
+
+    // end of a group of pointer events that belong together
+    Frame,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
@@ -31,6 +66,16 @@ pub enum KeyboardEvent {
         locked: u32,
         group: u32,
     },
+
+    // a key producing a character, resolved with the sender's layout,
+    // see `keysym`. `key` is the key typing it, for the sender to know
+    // what is held, the receiver goes by the keysym
+    Keysym {
+        time: u32,
+        key: u32,
+        keysym: u32,
+        state: u8,
+    },
 }
 
 #[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
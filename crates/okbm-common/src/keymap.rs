@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// Keyboard layout of the capturing side, installed on the virtual keyboard
+/// of the receiving side so keys produce the characters the user expects.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Keymap {
+    // keymap in the XKB text format
+    pub xkb: String,
+    // active layout
+    pub group: u32,
+}
+
+/// Key repeat of a keyboard, as in `wl_keyboard::repeat_info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepeatInfo {
+    // keys per second, 0 disables repeat
+    pub rate: u32,
+    // milliseconds before a held key starts repeating
+    pub delay: u32,
+}
+
+impl Default for RepeatInfo {
+    fn default() -> Self {
+        Self {
+            rate: 31,
+            delay: 500,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+/*
+ * characters as X11 keysyms, used when keys are forwarded by the
+ * character they produce rather than by their position
+ */
+
+use serde::{Deserialize, Serialize};
+
+/// How keys are forwarded to peers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyMode {
+    // the key's position, interpreted with the receiver's layout
+    #[default]
+    Scancode,
+    // the character produced with the sender's layout, keys that
+    // don't produce one (modifiers, arrows, shortcuts) are still sent as scancodes
+    Keysym,
+}
+
+// keysyms of unicode characters outside of latin-1 have this bit set
+const UNICODE_KEYSYM: u32 = 0x0100_0000;
+
+/// The keysym of `c`, latin-1 characters have their own keysym.
+pub fn from_char(c: char) -> u32 {
+    match c as u32 {
+        cp @ (0x20..=0x7e | 0xa0..=0xff) => cp,
+        cp => UNICODE_KEYSYM | cp,
+    }
+}
+
+/// The character of a keysym built by `from_char`.
+pub fn to_char(keysym: u32) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        _ if keysym & 0xff00_0000 == UNICODE_KEYSYM => char::from_u32(keysym & 0x00ff_ffff),
+        _ => None,
+    }
+}
+
+/// Whether `c` is worth sending as a keysym, control characters are left to scancodes.
+pub fn is_printable(c: char) -> bool {
+    !c.is_control()
+}
@@ -1,4 +1,18 @@
 mod event;
 pub use event::*;
 
+mod keymap;
+pub use keymap::*;
+
+mod modifiers;
+pub use modifiers::*;
+
+pub mod keysym;
+pub use keysym::KeyMode;
 pub mod scancode;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub mod xkb;
+
+#[cfg(target_os = "macos")]
+pub mod objc;
@@ -0,0 +1,278 @@
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+
+use crate::scancode::Linux;
+
+// From X11/X.h, the real modifiers of xkb masks
+bitflags! {
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct XMods: u32 {
+        const ShiftMask = (1<<0);
+        const LockMask = (1<<1);
+        const ControlMask = (1<<2);
+        const Mod1Mask = (1<<3);
+        const Mod2Mask = (1<<4);
+        const Mod3Mask = (1<<5);
+        const Mod4Mask = (1<<6);
+        const Mod5Mask = (1<<7);
+    }
+}
+
+bitflags! {
+    /// Held modifier keys, left and right apart.
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+    pub struct ModifierKeys: u8 {
+        const LeftShift = (1<<0);
+        const RightShift = (1<<1);
+        const LeftCtrl = (1<<2);
+        const RightCtrl = (1<<3);
+        const LeftAlt = (1<<4);
+        const RightAlt = (1<<5);
+        const LeftMeta = (1<<6);
+        const RightMeta = (1<<7);
+
+        const Shift = Self::LeftShift.bits() | Self::RightShift.bits();
+        const Ctrl = Self::LeftCtrl.bits() | Self::RightCtrl.bits();
+        const Alt = Self::LeftAlt.bits() | Self::RightAlt.bits();
+        const Meta = Self::LeftMeta.bits() | Self::RightMeta.bits();
+    }
+}
+
+/*
+ * CGEventFlags bits, the device independent ones and the
+ * device dependent NX_DEVICE*KEYMASK of IOKit/hidsystem/IOLLEvent.h
+ * telling left and right apart
+ */
+const CG_FLAG_ALPHA_SHIFT: u64 = 0x0001_0000;
+const CG_FLAG_SHIFT: u64 = 0x0002_0000;
+const CG_FLAG_CONTROL: u64 = 0x0004_0000;
+const CG_FLAG_ALTERNATE: u64 = 0x0008_0000;
+const CG_FLAG_COMMAND: u64 = 0x0010_0000;
+const CG_DEVICE_FLAGS: [(u64, ModifierKeys); 8] = [
+    (0x0001, ModifierKeys::LeftCtrl),
+    (0x0002, ModifierKeys::LeftShift),
+    (0x0004, ModifierKeys::RightShift),
+    (0x0008, ModifierKeys::LeftMeta),
+    (0x0010, ModifierKeys::RightMeta),
+    (0x0020, ModifierKeys::LeftAlt),
+    (0x0040, ModifierKeys::RightAlt),
+    (0x2000, ModifierKeys::RightCtrl),
+];
+
+/// Modifier state shared by all backends: held modifier keys plus
+/// latched and locked xkb modifiers and the active layout.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Modifiers {
+    pub pressed: ModifierKeys,
+    pub latched: XMods,
+    pub locked: XMods,
+    pub group: u32,
+}
+
+impl ModifierKeys {
+    pub fn from_key(key: Linux) -> Self {
+        match key {
+            Linux::KeyLeftShift => Self::LeftShift,
+            Linux::KeyRightShift => Self::RightShift,
+            Linux::KeyLeftCtrl => Self::LeftCtrl,
+            Linux::KeyRightCtrl => Self::RightCtrl,
+            Linux::KeyLeftAlt => Self::LeftAlt,
+            Linux::KeyRightalt => Self::RightAlt,
+            Linux::KeyLeftMeta => Self::LeftMeta,
+            Linux::KeyRightmeta => Self::RightMeta,
+            _ => Self::empty(),
+        }
+    }
+
+    /// The xkb modifiers these keys set in the default keymaps,
+    /// right alt is AltGr (ISO_Level3_Shift) on Mod5 in most layouts.
+    pub fn to_xmods(self) -> XMods {
+        let mut mods = XMods::empty();
+        for (keys, mask) in [
+            (Self::Shift, XMods::ShiftMask),
+            (Self::Ctrl, XMods::ControlMask),
+            (Self::LeftAlt, XMods::Mod1Mask),
+            (Self::RightAlt, XMods::Mod5Mask),
+            (Self::Meta, XMods::Mod4Mask),
+        ] {
+            if self.intersects(keys) {
+                mods |= mask;
+            }
+        }
+        mods
+    }
+}
+
+/// Which side's Caps/Num/Scroll Lock state applies when control enters a peer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockSync {
+    // the receiver toggles its locks to match the sender's
+    #[default]
+    Sender,
+    // the receiver keeps its locks and ignores the sender's. On Wayland the compositor
+    // doesn't tell us the locks of the local keyboard, the ones kept are those of the
+    // emulated keyboard, all off until a peer toggles them
+    Receiver,
+}
+
+// lock keys and the xkb modifier they lock
+const LOCK_KEYS: [(Linux, XMods); 3] = [
+    (Linux::KeyCapsLock, XMods::LockMask),
+    (Linux::KeyNumlock, XMods::Mod2Mask),
+    (Linux::KeyScrollLock, XMods::Mod3Mask),
+];
+
+impl XMods {
+    /// Caps, Num and Scroll Lock.
+    pub const LOCKS: Self = Self::LockMask.union(Self::Mod2Mask).union(Self::Mod3Mask);
+}
+
+fn lock_mask(key: Linux) -> XMods {
+    LOCK_KEYS
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, mask)| *mask)
+        .unwrap_or_default()
+}
+
+impl Modifiers {
+    /// Updates the state from a key event, returns whether it changed.
+    pub fn update_key(&mut self, key: u32, state: u8) -> bool {
+        let Ok(key) = Linux::try_from(key) else {
+            return false;
+        };
+        let keys = ModifierKeys::from_key(key);
+        let lock = lock_mask(key);
+        let before = *self;
+        match state {
+            1 => {
+                self.pressed.insert(keys);
+                // locks toggle on press like xkb does
+                self.locked.toggle(lock);
+                // latches are consumed by the next key
+                if keys.is_empty() && lock.is_empty() {
+                    self.latched = XMods::empty();
+                }
+            }
+            _ => self.pressed.remove(keys),
+        }
+        *self != before
+    }
+
+    /// Updates the state from xkb masks. Masks can't tell left from right,
+    /// keys that are still held keep their side and new ones are assumed on the left.
+    pub fn update_xkb(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        let depressed = XMods::from_bits_truncate(depressed);
+        let mut pressed = ModifierKeys::empty();
+        for (keys, left, mask) in [
+            (
+                ModifierKeys::Shift,
+                ModifierKeys::LeftShift,
+                XMods::ShiftMask,
+            ),
+            (
+                ModifierKeys::Ctrl,
+                ModifierKeys::LeftCtrl,
+                XMods::ControlMask,
+            ),
+            (ModifierKeys::Alt, ModifierKeys::LeftAlt, XMods::Mod1Mask),
+            (
+                ModifierKeys::RightAlt,
+                ModifierKeys::RightAlt,
+                XMods::Mod5Mask,
+            ),
+            (ModifierKeys::Meta, ModifierKeys::LeftMeta, XMods::Mod4Mask),
+        ] {
+            if depressed.contains(mask) {
+                let held = self.pressed & keys;
+                pressed |= if held.is_empty() { left } else { held };
+            }
+        }
+        self.pressed = pressed;
+        self.latched = XMods::from_bits_truncate(latched);
+        self.locked = XMods::from_bits_truncate(locked);
+        self.group = group;
+    }
+
+    /// The lock keys to tap for the locks to become those of `locked`.
+    pub fn lock_keys_to(&self, locked: XMods) -> Vec<Linux> {
+        let diff = (self.locked ^ locked) & XMods::LOCKS;
+        LOCK_KEYS
+            .iter()
+            .filter(|(_, mask)| diff.contains(*mask))
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    pub fn depressed(&self) -> XMods {
+        self.pressed.to_xmods()
+    }
+
+    /// (depressed, latched, locked, group) as in a wl_keyboard modifiers event.
+    pub fn xkb_masks(&self) -> (u32, u32, u32, u32) {
+        (
+            self.depressed().bits(),
+            self.latched.bits(),
+            self.locked.bits(),
+            self.group,
+        )
+    }
+
+    /// Reads the bits of a macOS CGEventFlags, caps lock is the only lock there.
+    pub fn from_cg_flags(flags: u64) -> Self {
+        let mut pressed = ModifierKeys::empty();
+        for (bit, key) in CG_DEVICE_FLAGS {
+            if flags & bit != 0 {
+                pressed |= key;
+            }
+        }
+        // synthetic events may only set the device independent bits
+        for (flag, keys, left) in [
+            (CG_FLAG_SHIFT, ModifierKeys::Shift, ModifierKeys::LeftShift),
+            (CG_FLAG_CONTROL, ModifierKeys::Ctrl, ModifierKeys::LeftCtrl),
+            (CG_FLAG_ALTERNATE, ModifierKeys::Alt, ModifierKeys::LeftAlt),
+            (CG_FLAG_COMMAND, ModifierKeys::Meta, ModifierKeys::LeftMeta),
+        ] {
+            if flags & flag == 0 {
+                pressed -= keys;
+            } else if !pressed.intersects(keys) {
+                pressed |= left;
+            }
+        }
+        let mut locked = XMods::empty();
+        if flags & CG_FLAG_ALPHA_SHIFT != 0 {
+            locked |= XMods::LockMask;
+        }
+        Self {
+            pressed,
+            locked,
+            ..Default::default()
+        }
+    }
+
+    /// The bits of a macOS CGEventFlags for this state.
+    pub fn cg_flags(&self) -> u64 {
+        let mut flags = 0;
+        for (bit, key) in CG_DEVICE_FLAGS {
+            if self.pressed.contains(key) {
+                flags |= bit;
+            }
+        }
+        for (flag, keys) in [
+            (CG_FLAG_SHIFT, ModifierKeys::Shift),
+            (CG_FLAG_CONTROL, ModifierKeys::Ctrl),
+            (CG_FLAG_ALTERNATE, ModifierKeys::Alt),
+            (CG_FLAG_COMMAND, ModifierKeys::Meta),
+        ] {
+            if self.pressed.intersects(keys) {
+                flags |= flag;
+            }
+        }
+        if self.locked.contains(XMods::LockMask) {
+            flags |= CG_FLAG_ALPHA_SHIFT;
+        }
+        flags
+    }
+}
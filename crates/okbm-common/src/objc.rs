@@ -0,0 +1,87 @@
+use std::{
+    ffi::{CStr, c_char, c_void},
+    mem,
+};
+
+/*
+ * minimal bindings to the Objective-C runtime and AppKit,
+ * shared by the macOS backends
+ */
+
+pub type Id = *mut c_void;
+pub type Sel = *const c_void;
+
+#[link(name = "AppKit", kind = "framework")]
+unsafe extern "C" {}
+
+#[link(name = "objc")]
+unsafe extern "C" {
+    fn objc_getClass(name: *const c_char) -> Id;
+    fn sel_registerName(name: *const c_char) -> Sel;
+    fn objc_msgSend();
+    pub fn objc_autoreleasePoolPush() -> *mut c_void;
+    pub fn objc_autoreleasePoolPop(pool: *mut c_void);
+}
+
+/*
+ * objc_msgSend has to be called through a pointer with the signature
+ * of the method it dispatches to
+ */
+
+/// Sends `selector` to `receiver`.
+///
+/// # Safety
+/// `receiver` must be an object or nil and `R` the return type of the method.
+pub unsafe fn send<R>(receiver: Id, selector: &CStr) -> R {
+    unsafe {
+        let f: unsafe extern "C" fn(Id, Sel) -> R =
+            mem::transmute(objc_msgSend as unsafe extern "C" fn());
+        f(receiver, sel_registerName(selector.as_ptr()))
+    }
+}
+
+/// Sends `selector` with an argument, see `send`.
+///
+/// # Safety
+/// As for `send`, `A` must be the type of the method's argument.
+pub unsafe fn send1<A, R>(receiver: Id, selector: &CStr, a: A) -> R {
+    unsafe {
+        let f: unsafe extern "C" fn(Id, Sel, A) -> R =
+            mem::transmute(objc_msgSend as unsafe extern "C" fn());
+        f(receiver, sel_registerName(selector.as_ptr()), a)
+    }
+}
+
+/// Sends `selector` with two arguments, see `send`.
+///
+/// # Safety
+/// As for `send`, `A` and `B` must be the types of the method's arguments.
+pub unsafe fn send2<A, B, R>(receiver: Id, selector: &CStr, a: A, b: B) -> R {
+    unsafe {
+        let f: unsafe extern "C" fn(Id, Sel, A, B) -> R =
+            mem::transmute(objc_msgSend as unsafe extern "C" fn());
+        f(receiver, sel_registerName(selector.as_ptr()), a, b)
+    }
+}
+
+/// The class named `name`, nil if there is none.
+///
+/// # Safety
+/// The runtime must be loaded, which linking AppKit does.
+pub unsafe fn class(name: &CStr) -> Id {
+    unsafe { objc_getClass(name.as_ptr()) }
+}
+
+/// An autoreleased NSString of `string`.
+///
+/// # Safety
+/// An autorelease pool must be in place.
+pub unsafe fn ns_string(string: &CStr) -> Id {
+    unsafe {
+        send1(
+            class(c"NSString"),
+            c"stringWithUTF8String:",
+            string.as_ptr(),
+        )
+    }
+}
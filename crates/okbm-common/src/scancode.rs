@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
  * https://kbd-project.org/docs/scancodes/scancodes-1.html
  */
 #[repr(u32)]
-#[derive(Debug, Clone, Copy, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, TryFromPrimitive)]
 pub enum Windows {
     Shutdown = 0xE05E,
     SystemSleep = 0xE05F,
@@ -698,7 +698,7 @@ impl TryFrom<Windows> for Linux {
             Windows::Shutdown => Ok(Self::KeyPower),
             Windows::SystemSleep => Ok(Self::KeySleep),
             Windows::SystemWakeUp => Ok(Self::KeyWakeup),
-            Windows::ErrorRollOver => Ok(Self::KeyRo),
+            Windows::ErrorRollOver => Err(()), // not a key
             Windows::KeyA => Ok(Self::KeyA),
             Windows::KeyB => Ok(Self::KeyB),
             Windows::KeyC => Ok(Self::KeyC),
@@ -849,3 +849,878 @@ impl TryFrom<Windows> for Linux {
         }
     }
 }
+
+/*
+ * keyboard page (0x07) of https://usb.org/document-library/hid-usage-tables-15
+ * https://github.com/torvalds/linux/blob/master/drivers/hid/hid-input.c
+ */
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, TryFromPrimitive)]
+pub enum Hid {
+    KeyA = 0x04,
+    KeyB = 0x05,
+    KeyC = 0x06,
+    KeyD = 0x07,
+    KeyE = 0x08,
+    KeyF = 0x09,
+    KeyG = 0x0A,
+    KeyH = 0x0B,
+    KeyI = 0x0C,
+    KeyJ = 0x0D,
+    KeyK = 0x0E,
+    KeyL = 0x0F,
+    KeyM = 0x10,
+    KeyN = 0x11,
+    KeyO = 0x12,
+    KeyP = 0x13,
+    KeyQ = 0x14,
+    KeyR = 0x15,
+    KeyS = 0x16,
+    KeyT = 0x17,
+    KeyU = 0x18,
+    KeyV = 0x19,
+    KeyW = 0x1A,
+    KeyX = 0x1B,
+    KeyY = 0x1C,
+    KeyZ = 0x1D,
+    Key1 = 0x1E,
+    Key2 = 0x1F,
+    Key3 = 0x20,
+    Key4 = 0x21,
+    Key5 = 0x22,
+    Key6 = 0x23,
+    Key7 = 0x24,
+    Key8 = 0x25,
+    Key9 = 0x26,
+    Key0 = 0x27,
+    KeyEnter = 0x28,
+    KeyEsc = 0x29,
+    KeyBackspace = 0x2A,
+    KeyTab = 0x2B,
+    KeySpace = 0x2C,
+    KeyMinus = 0x2D,
+    KeyEqual = 0x2E,
+    KeyLeftBrace = 0x2F,
+    KeyRightBrace = 0x30,
+    KeyBackslash = 0x31,
+    KeyNonUSHash = 0x32,
+    KeySemicolon = 0x33,
+    KeyApostrophe = 0x34,
+    KeyGrave = 0x35,
+    KeyComma = 0x36,
+    KeyDot = 0x37,
+    KeySlash = 0x38,
+    KeyCapsLock = 0x39,
+    KeyF1 = 0x3A,
+    KeyF2 = 0x3B,
+    KeyF3 = 0x3C,
+    KeyF4 = 0x3D,
+    KeyF5 = 0x3E,
+    KeyF6 = 0x3F,
+    KeyF7 = 0x40,
+    KeyF8 = 0x41,
+    KeyF9 = 0x42,
+    KeyF10 = 0x43,
+    KeyF11 = 0x44,
+    KeyF12 = 0x45,
+    KeyPrintScreen = 0x46,
+    KeyScrollLock = 0x47,
+    KeyPause = 0x48,
+    KeyInsert = 0x49,
+    KeyHome = 0x4A,
+    KeyPageUp = 0x4B,
+    KeyDeleteForward = 0x4C,
+    KeyEnd = 0x4D,
+    KeyPageDown = 0x4E,
+    KeyRight = 0x4F,
+    KeyLeft = 0x50,
+    KeyDown = 0x51,
+    KeyUp = 0x52,
+    KeypadNumLock = 0x53,
+    KeypadSlash = 0x54,
+    KeypadStar = 0x55,
+    KeypadMinus = 0x56,
+    KeypadPlus = 0x57,
+    KeypadEnter = 0x58,
+    Keypad1 = 0x59,
+    Keypad2 = 0x5A,
+    Keypad3 = 0x5B,
+    Keypad4 = 0x5C,
+    Keypad5 = 0x5D,
+    Keypad6 = 0x5E,
+    Keypad7 = 0x5F,
+    Keypad8 = 0x60,
+    Keypad9 = 0x61,
+    Keypad0 = 0x62,
+    KeypadDot = 0x63,
+    KeyNonUSBackslash = 0x64,
+    KeyApplication = 0x65,
+    KeyPower = 0x66,
+    KeypadEqual = 0x67,
+    KeyF13 = 0x68,
+    KeyF14 = 0x69,
+    KeyF15 = 0x6A,
+    KeyF16 = 0x6B,
+    KeyF17 = 0x6C,
+    KeyF18 = 0x6D,
+    KeyF19 = 0x6E,
+    KeyF20 = 0x6F,
+    KeyF21 = 0x70,
+    KeyF22 = 0x71,
+    KeyF23 = 0x72,
+    KeyF24 = 0x73,
+    KeyExecute = 0x74,
+    KeyHelp = 0x75,
+    KeyMenu = 0x76,
+    KeySelect = 0x77,
+    KeyStop = 0x78,
+    KeyAgain = 0x79,
+    KeyUndo = 0x7A,
+    KeyCut = 0x7B,
+    KeyCopy = 0x7C,
+    KeyPaste = 0x7D,
+    KeyFind = 0x7E,
+    KeyMute = 0x7F,
+    KeyVolumeUp = 0x80,
+    KeyVolumeDown = 0x81,
+    KeypadComma = 0x85,
+    KeyInternational1 = 0x87,
+    KeyInternational2 = 0x88,
+    KeyInternational3 = 0x89,
+    KeyInternational4 = 0x8A,
+    KeyInternational5 = 0x8B,
+    KeyInternational6 = 0x8C,
+    KeyLANG1 = 0x90,
+    KeyLANG2 = 0x91,
+    KeyLANG3 = 0x92,
+    KeyLANG4 = 0x93,
+    KeyLANG5 = 0x94,
+    KeypadLeftParen = 0xB6,
+    KeypadRightParen = 0xB7,
+    KeyLeftCtrl = 0xE0,
+    KeyLeftShift = 0xE1,
+    KeyLeftAlt = 0xE2,
+    KeyLeftGUI = 0xE3,
+    KeyRightCtrl = 0xE4,
+    KeyRightShift = 0xE5,
+    KeyRightAlt = 0xE6,
+    KeyRightGUI = 0xE7,
+}
+
+impl TryFrom<Linux> for Hid {
+    type Error = ();
+
+    fn try_from(value: Linux) -> Result<Self, Self::Error> {
+        match value {
+            Linux::KeyA => Ok(Self::KeyA),
+            Linux::KeyB => Ok(Self::KeyB),
+            Linux::KeyC => Ok(Self::KeyC),
+            Linux::KeyD => Ok(Self::KeyD),
+            Linux::KeyE => Ok(Self::KeyE),
+            Linux::KeyF => Ok(Self::KeyF),
+            Linux::KeyG => Ok(Self::KeyG),
+            Linux::KeyH => Ok(Self::KeyH),
+            Linux::KeyI => Ok(Self::KeyI),
+            Linux::KeyJ => Ok(Self::KeyJ),
+            Linux::KeyK => Ok(Self::KeyK),
+            Linux::KeyL => Ok(Self::KeyL),
+            Linux::KeyM => Ok(Self::KeyM),
+            Linux::KeyN => Ok(Self::KeyN),
+            Linux::KeyO => Ok(Self::KeyO),
+            Linux::KeyP => Ok(Self::KeyP),
+            Linux::KeyQ => Ok(Self::KeyQ),
+            Linux::KeyR => Ok(Self::KeyR),
+            Linux::KeyS => Ok(Self::KeyS),
+            Linux::KeyT => Ok(Self::KeyT),
+            Linux::KeyU => Ok(Self::KeyU),
+            Linux::KeyV => Ok(Self::KeyV),
+            Linux::KeyW => Ok(Self::KeyW),
+            Linux::KeyX => Ok(Self::KeyX),
+            Linux::KeyY => Ok(Self::KeyY),
+            Linux::KeyZ => Ok(Self::KeyZ),
+            Linux::Key1 => Ok(Self::Key1),
+            Linux::Key2 => Ok(Self::Key2),
+            Linux::Key3 => Ok(Self::Key3),
+            Linux::Key4 => Ok(Self::Key4),
+            Linux::Key5 => Ok(Self::Key5),
+            Linux::Key6 => Ok(Self::Key6),
+            Linux::Key7 => Ok(Self::Key7),
+            Linux::Key8 => Ok(Self::Key8),
+            Linux::Key9 => Ok(Self::Key9),
+            Linux::Key0 => Ok(Self::Key0),
+            Linux::KeyEnter => Ok(Self::KeyEnter),
+            Linux::KeyEsc => Ok(Self::KeyEsc),
+            Linux::KeyBackspace => Ok(Self::KeyBackspace),
+            Linux::KeyTab => Ok(Self::KeyTab),
+            Linux::KeySpace => Ok(Self::KeySpace),
+            Linux::KeyMinus => Ok(Self::KeyMinus),
+            Linux::KeyEqual => Ok(Self::KeyEqual),
+            Linux::KeyLeftbrace => Ok(Self::KeyLeftBrace),
+            Linux::KeyRightbrace => Ok(Self::KeyRightBrace),
+            Linux::KeyBackslash => Ok(Self::KeyBackslash),
+            Linux::KeySemicolon => Ok(Self::KeySemicolon),
+            Linux::KeyApostrophe => Ok(Self::KeyApostrophe),
+            Linux::KeyGrave => Ok(Self::KeyGrave),
+            Linux::KeyComma => Ok(Self::KeyComma),
+            Linux::KeyDot => Ok(Self::KeyDot),
+            Linux::KeySlash => Ok(Self::KeySlash),
+            Linux::KeyCapsLock => Ok(Self::KeyCapsLock),
+            Linux::KeyF1 => Ok(Self::KeyF1),
+            Linux::KeyF2 => Ok(Self::KeyF2),
+            Linux::KeyF3 => Ok(Self::KeyF3),
+            Linux::KeyF4 => Ok(Self::KeyF4),
+            Linux::KeyF5 => Ok(Self::KeyF5),
+            Linux::KeyF6 => Ok(Self::KeyF6),
+            Linux::KeyF7 => Ok(Self::KeyF7),
+            Linux::KeyF8 => Ok(Self::KeyF8),
+            Linux::KeyF9 => Ok(Self::KeyF9),
+            Linux::KeyF10 => Ok(Self::KeyF10),
+            Linux::KeyF11 => Ok(Self::KeyF11),
+            Linux::KeyF12 => Ok(Self::KeyF12),
+            Linux::KeySysrq => Ok(Self::KeyPrintScreen),
+            Linux::KeyScrollLock => Ok(Self::KeyScrollLock),
+            Linux::KeyPause => Ok(Self::KeyPause),
+            Linux::KeyInsert => Ok(Self::KeyInsert),
+            Linux::KeyHome => Ok(Self::KeyHome),
+            Linux::KeyPageup => Ok(Self::KeyPageUp),
+            Linux::KeyDelete => Ok(Self::KeyDeleteForward),
+            Linux::KeyEnd => Ok(Self::KeyEnd),
+            Linux::KeyPagedown => Ok(Self::KeyPageDown),
+            Linux::KeyRight => Ok(Self::KeyRight),
+            Linux::KeyLeft => Ok(Self::KeyLeft),
+            Linux::KeyDown => Ok(Self::KeyDown),
+            Linux::KeyUp => Ok(Self::KeyUp),
+            Linux::KeyNumlock => Ok(Self::KeypadNumLock),
+            Linux::KeyKpslash => Ok(Self::KeypadSlash),
+            Linux::KeyKpAsterisk => Ok(Self::KeypadStar),
+            Linux::KeyKpMinus => Ok(Self::KeypadMinus),
+            Linux::KeyKpplus => Ok(Self::KeypadPlus),
+            Linux::KeyKpEnter => Ok(Self::KeypadEnter),
+            Linux::KeyKp1 => Ok(Self::Keypad1),
+            Linux::KeyKp2 => Ok(Self::Keypad2),
+            Linux::KeyKp3 => Ok(Self::Keypad3),
+            Linux::KeyKp4 => Ok(Self::Keypad4),
+            Linux::KeyKp5 => Ok(Self::Keypad5),
+            Linux::KeyKp6 => Ok(Self::Keypad6),
+            Linux::KeyKp7 => Ok(Self::Keypad7),
+            Linux::KeyKp8 => Ok(Self::Keypad8),
+            Linux::KeyKp9 => Ok(Self::Keypad9),
+            Linux::KeyKp0 => Ok(Self::Keypad0),
+            Linux::KeyKpDot => Ok(Self::KeypadDot),
+            Linux::Key102nd => Ok(Self::KeyNonUSBackslash),
+            Linux::KeyCompose => Ok(Self::KeyApplication),
+            Linux::KeyPower => Ok(Self::KeyPower),
+            Linux::KeyKpequal => Ok(Self::KeypadEqual),
+            Linux::KeyF13 => Ok(Self::KeyF13),
+            Linux::KeyF14 => Ok(Self::KeyF14),
+            Linux::KeyF15 => Ok(Self::KeyF15),
+            Linux::KeyF16 => Ok(Self::KeyF16),
+            Linux::KeyF17 => Ok(Self::KeyF17),
+            Linux::KeyF18 => Ok(Self::KeyF18),
+            Linux::KeyF19 => Ok(Self::KeyF19),
+            Linux::KeyF20 => Ok(Self::KeyF20),
+            Linux::KeyF21 => Ok(Self::KeyF21),
+            Linux::KeyF22 => Ok(Self::KeyF22),
+            Linux::KeyF23 => Ok(Self::KeyF23),
+            Linux::KeyF24 => Ok(Self::KeyF24),
+            Linux::KeyOpen => Ok(Self::KeyExecute),
+            Linux::KeyHelp => Ok(Self::KeyHelp),
+            Linux::KeyProps => Ok(Self::KeyMenu),
+            Linux::KeyFront => Ok(Self::KeySelect),
+            Linux::KeyStop => Ok(Self::KeyStop),
+            Linux::KeyAgain => Ok(Self::KeyAgain),
+            Linux::KeyUndo => Ok(Self::KeyUndo),
+            Linux::KeyCut => Ok(Self::KeyCut),
+            Linux::KeyCopy => Ok(Self::KeyCopy),
+            Linux::KeyPaste => Ok(Self::KeyPaste),
+            Linux::KeyFind => Ok(Self::KeyFind),
+            Linux::KeyMute => Ok(Self::KeyMute),
+            Linux::KeyVolumeUp => Ok(Self::KeyVolumeUp),
+            Linux::KeyVolumeDown => Ok(Self::KeyVolumeDown),
+            Linux::KeyKpcomma => Ok(Self::KeypadComma),
+            Linux::KeyRo => Ok(Self::KeyInternational1),
+            Linux::KeyKatakanahiragana => Ok(Self::KeyInternational2),
+            Linux::KeyYen => Ok(Self::KeyInternational3),
+            Linux::KeyHenkan => Ok(Self::KeyInternational4),
+            Linux::KeyMuhenkan => Ok(Self::KeyInternational5),
+            Linux::KeyKpJpComma => Ok(Self::KeyInternational6),
+            Linux::KeyHanguel => Ok(Self::KeyLANG1),
+            Linux::KeyHanja => Ok(Self::KeyLANG2),
+            Linux::KeyKatakana => Ok(Self::KeyLANG3),
+            Linux::KeyHiragana => Ok(Self::KeyLANG4),
+            Linux::KeyZenkakuhankaku => Ok(Self::KeyLANG5),
+            Linux::KeyKpleftparen => Ok(Self::KeypadLeftParen),
+            Linux::KeyKprightparen => Ok(Self::KeypadRightParen),
+            Linux::KeyLeftCtrl => Ok(Self::KeyLeftCtrl),
+            Linux::KeyLeftShift => Ok(Self::KeyLeftShift),
+            Linux::KeyLeftAlt => Ok(Self::KeyLeftAlt),
+            Linux::KeyLeftMeta => Ok(Self::KeyLeftGUI),
+            Linux::KeyRightCtrl => Ok(Self::KeyRightCtrl),
+            Linux::KeyRightShift => Ok(Self::KeyRightShift),
+            Linux::KeyRightalt => Ok(Self::KeyRightAlt),
+            Linux::KeyRightmeta => Ok(Self::KeyRightGUI),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<Hid> for Linux {
+    fn from(value: Hid) -> Self {
+        match value {
+            Hid::KeyA => Self::KeyA,
+            Hid::KeyB => Self::KeyB,
+            Hid::KeyC => Self::KeyC,
+            Hid::KeyD => Self::KeyD,
+            Hid::KeyE => Self::KeyE,
+            Hid::KeyF => Self::KeyF,
+            Hid::KeyG => Self::KeyG,
+            Hid::KeyH => Self::KeyH,
+            Hid::KeyI => Self::KeyI,
+            Hid::KeyJ => Self::KeyJ,
+            Hid::KeyK => Self::KeyK,
+            Hid::KeyL => Self::KeyL,
+            Hid::KeyM => Self::KeyM,
+            Hid::KeyN => Self::KeyN,
+            Hid::KeyO => Self::KeyO,
+            Hid::KeyP => Self::KeyP,
+            Hid::KeyQ => Self::KeyQ,
+            Hid::KeyR => Self::KeyR,
+            Hid::KeyS => Self::KeyS,
+            Hid::KeyT => Self::KeyT,
+            Hid::KeyU => Self::KeyU,
+            Hid::KeyV => Self::KeyV,
+            Hid::KeyW => Self::KeyW,
+            Hid::KeyX => Self::KeyX,
+            Hid::KeyY => Self::KeyY,
+            Hid::KeyZ => Self::KeyZ,
+            Hid::Key1 => Self::Key1,
+            Hid::Key2 => Self::Key2,
+            Hid::Key3 => Self::Key3,
+            Hid::Key4 => Self::Key4,
+            Hid::Key5 => Self::Key5,
+            Hid::Key6 => Self::Key6,
+            Hid::Key7 => Self::Key7,
+            Hid::Key8 => Self::Key8,
+            Hid::Key9 => Self::Key9,
+            Hid::Key0 => Self::Key0,
+            Hid::KeyEnter => Self::KeyEnter,
+            Hid::KeyEsc => Self::KeyEsc,
+            Hid::KeyBackspace => Self::KeyBackspace,
+            Hid::KeyTab => Self::KeyTab,
+            Hid::KeySpace => Self::KeySpace,
+            Hid::KeyMinus => Self::KeyMinus,
+            Hid::KeyEqual => Self::KeyEqual,
+            Hid::KeyLeftBrace => Self::KeyLeftbrace,
+            Hid::KeyRightBrace => Self::KeyRightbrace,
+            Hid::KeyBackslash => Self::KeyBackslash,
+            Hid::KeyNonUSHash => Self::KeyBackslash, // same key as KeyBackslash on ISO keyboards
+            Hid::KeySemicolon => Self::KeySemicolon,
+            Hid::KeyApostrophe => Self::KeyApostrophe,
+            Hid::KeyGrave => Self::KeyGrave,
+            Hid::KeyComma => Self::KeyComma,
+            Hid::KeyDot => Self::KeyDot,
+            Hid::KeySlash => Self::KeySlash,
+            Hid::KeyCapsLock => Self::KeyCapsLock,
+            Hid::KeyF1 => Self::KeyF1,
+            Hid::KeyF2 => Self::KeyF2,
+            Hid::KeyF3 => Self::KeyF3,
+            Hid::KeyF4 => Self::KeyF4,
+            Hid::KeyF5 => Self::KeyF5,
+            Hid::KeyF6 => Self::KeyF6,
+            Hid::KeyF7 => Self::KeyF7,
+            Hid::KeyF8 => Self::KeyF8,
+            Hid::KeyF9 => Self::KeyF9,
+            Hid::KeyF10 => Self::KeyF10,
+            Hid::KeyF11 => Self::KeyF11,
+            Hid::KeyF12 => Self::KeyF12,
+            Hid::KeyPrintScreen => Self::KeySysrq,
+            Hid::KeyScrollLock => Self::KeyScrollLock,
+            Hid::KeyPause => Self::KeyPause,
+            Hid::KeyInsert => Self::KeyInsert,
+            Hid::KeyHome => Self::KeyHome,
+            Hid::KeyPageUp => Self::KeyPageup,
+            Hid::KeyDeleteForward => Self::KeyDelete,
+            Hid::KeyEnd => Self::KeyEnd,
+            Hid::KeyPageDown => Self::KeyPagedown,
+            Hid::KeyRight => Self::KeyRight,
+            Hid::KeyLeft => Self::KeyLeft,
+            Hid::KeyDown => Self::KeyDown,
+            Hid::KeyUp => Self::KeyUp,
+            Hid::KeypadNumLock => Self::KeyNumlock,
+            Hid::KeypadSlash => Self::KeyKpslash,
+            Hid::KeypadStar => Self::KeyKpAsterisk,
+            Hid::KeypadMinus => Self::KeyKpMinus,
+            Hid::KeypadPlus => Self::KeyKpplus,
+            Hid::KeypadEnter => Self::KeyKpEnter,
+            Hid::Keypad1 => Self::KeyKp1,
+            Hid::Keypad2 => Self::KeyKp2,
+            Hid::Keypad3 => Self::KeyKp3,
+            Hid::Keypad4 => Self::KeyKp4,
+            Hid::Keypad5 => Self::KeyKp5,
+            Hid::Keypad6 => Self::KeyKp6,
+            Hid::Keypad7 => Self::KeyKp7,
+            Hid::Keypad8 => Self::KeyKp8,
+            Hid::Keypad9 => Self::KeyKp9,
+            Hid::Keypad0 => Self::KeyKp0,
+            Hid::KeypadDot => Self::KeyKpDot,
+            Hid::KeyNonUSBackslash => Self::Key102nd,
+            Hid::KeyApplication => Self::KeyCompose,
+            Hid::KeyPower => Self::KeyPower,
+            Hid::KeypadEqual => Self::KeyKpequal,
+            Hid::KeyF13 => Self::KeyF13,
+            Hid::KeyF14 => Self::KeyF14,
+            Hid::KeyF15 => Self::KeyF15,
+            Hid::KeyF16 => Self::KeyF16,
+            Hid::KeyF17 => Self::KeyF17,
+            Hid::KeyF18 => Self::KeyF18,
+            Hid::KeyF19 => Self::KeyF19,
+            Hid::KeyF20 => Self::KeyF20,
+            Hid::KeyF21 => Self::KeyF21,
+            Hid::KeyF22 => Self::KeyF22,
+            Hid::KeyF23 => Self::KeyF23,
+            Hid::KeyF24 => Self::KeyF24,
+            Hid::KeyExecute => Self::KeyOpen,
+            Hid::KeyHelp => Self::KeyHelp,
+            Hid::KeyMenu => Self::KeyProps,
+            Hid::KeySelect => Self::KeyFront,
+            Hid::KeyStop => Self::KeyStop,
+            Hid::KeyAgain => Self::KeyAgain,
+            Hid::KeyUndo => Self::KeyUndo,
+            Hid::KeyCut => Self::KeyCut,
+            Hid::KeyCopy => Self::KeyCopy,
+            Hid::KeyPaste => Self::KeyPaste,
+            Hid::KeyFind => Self::KeyFind,
+            Hid::KeyMute => Self::KeyMute,
+            Hid::KeyVolumeUp => Self::KeyVolumeUp,
+            Hid::KeyVolumeDown => Self::KeyVolumeDown,
+            Hid::KeypadComma => Self::KeyKpcomma,
+            Hid::KeyInternational1 => Self::KeyRo,
+            Hid::KeyInternational2 => Self::KeyKatakanahiragana,
+            Hid::KeyInternational3 => Self::KeyYen,
+            Hid::KeyInternational4 => Self::KeyHenkan,
+            Hid::KeyInternational5 => Self::KeyMuhenkan,
+            Hid::KeyInternational6 => Self::KeyKpJpComma,
+            Hid::KeyLANG1 => Self::KeyHanguel,
+            Hid::KeyLANG2 => Self::KeyHanja,
+            Hid::KeyLANG3 => Self::KeyKatakana,
+            Hid::KeyLANG4 => Self::KeyHiragana,
+            Hid::KeyLANG5 => Self::KeyZenkakuhankaku,
+            Hid::KeypadLeftParen => Self::KeyKpleftparen,
+            Hid::KeypadRightParen => Self::KeyKprightparen,
+            Hid::KeyLeftCtrl => Self::KeyLeftCtrl,
+            Hid::KeyLeftShift => Self::KeyLeftShift,
+            Hid::KeyLeftAlt => Self::KeyLeftAlt,
+            Hid::KeyLeftGUI => Self::KeyLeftMeta,
+            Hid::KeyRightCtrl => Self::KeyRightCtrl,
+            Hid::KeyRightShift => Self::KeyRightShift,
+            Hid::KeyRightAlt => Self::KeyRightalt,
+            Hid::KeyRightGUI => Self::KeyRightmeta,
+        }
+    }
+}
+
+/*
+ * kVK_* constants of HIToolbox/Events.h
+ */
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, TryFromPrimitive)]
+pub enum MacOS {
+    KeyA = 0x00,
+    KeyS = 0x01,
+    KeyD = 0x02,
+    KeyF = 0x03,
+    KeyH = 0x04,
+    KeyG = 0x05,
+    KeyZ = 0x06,
+    KeyX = 0x07,
+    KeyC = 0x08,
+    KeyV = 0x09,
+    KeyIsoSection = 0x0A,
+    KeyB = 0x0B,
+    KeyQ = 0x0C,
+    KeyW = 0x0D,
+    KeyE = 0x0E,
+    KeyR = 0x0F,
+    KeyY = 0x10,
+    KeyT = 0x11,
+    Key1 = 0x12,
+    Key2 = 0x13,
+    Key3 = 0x14,
+    Key4 = 0x15,
+    Key6 = 0x16,
+    Key5 = 0x17,
+    KeyEqual = 0x18,
+    Key9 = 0x19,
+    Key7 = 0x1A,
+    KeyMinus = 0x1B,
+    Key8 = 0x1C,
+    Key0 = 0x1D,
+    KeyRightBracket = 0x1E,
+    KeyO = 0x1F,
+    KeyU = 0x20,
+    KeyLeftBracket = 0x21,
+    KeyI = 0x22,
+    KeyP = 0x23,
+    KeyReturn = 0x24,
+    KeyL = 0x25,
+    KeyJ = 0x26,
+    KeyQuote = 0x27,
+    KeyK = 0x28,
+    KeySemicolon = 0x29,
+    KeyBackslash = 0x2A,
+    KeyComma = 0x2B,
+    KeySlash = 0x2C,
+    KeyN = 0x2D,
+    KeyM = 0x2E,
+    KeyPeriod = 0x2F,
+    KeyTab = 0x30,
+    KeySpace = 0x31,
+    KeyGrave = 0x32,
+    KeyDelete = 0x33,
+    KeyEscape = 0x35,
+    KeyRightCommand = 0x36,
+    KeyCommand = 0x37,
+    KeyShift = 0x38,
+    KeyCapsLock = 0x39,
+    KeyOption = 0x3A,
+    KeyControl = 0x3B,
+    KeyRightShift = 0x3C,
+    KeyRightOption = 0x3D,
+    KeyRightControl = 0x3E,
+    KeyF17 = 0x40,
+    KeypadDecimal = 0x41,
+    KeypadMultiply = 0x43,
+    KeypadPlus = 0x45,
+    KeypadClear = 0x47,
+    KeyVolumeUp = 0x48,
+    KeyVolumeDown = 0x49,
+    KeyMute = 0x4A,
+    KeypadDivide = 0x4B,
+    KeypadEnter = 0x4C,
+    KeypadMinus = 0x4E,
+    KeyF18 = 0x4F,
+    KeyF19 = 0x50,
+    KeypadEquals = 0x51,
+    Keypad0 = 0x52,
+    Keypad1 = 0x53,
+    Keypad2 = 0x54,
+    Keypad3 = 0x55,
+    Keypad4 = 0x56,
+    Keypad5 = 0x57,
+    Keypad6 = 0x58,
+    Keypad7 = 0x59,
+    KeyF20 = 0x5A,
+    Keypad8 = 0x5B,
+    Keypad9 = 0x5C,
+    KeyJisYen = 0x5D,
+    KeyJisUnderscore = 0x5E,
+    KeyJisKeypadComma = 0x5F,
+    KeyF5 = 0x60,
+    KeyF6 = 0x61,
+    KeyF7 = 0x62,
+    KeyF3 = 0x63,
+    KeyF8 = 0x64,
+    KeyF9 = 0x65,
+    KeyJisEisu = 0x66,
+    KeyF11 = 0x67,
+    KeyJisKana = 0x68,
+    KeyF13 = 0x69,
+    KeyF16 = 0x6A,
+    KeyF14 = 0x6B,
+    KeyF10 = 0x6D,
+    KeyContextualMenu = 0x6E,
+    KeyF12 = 0x6F,
+    KeyF15 = 0x71,
+    KeyHelp = 0x72,
+    KeyHome = 0x73,
+    KeyPageUp = 0x74,
+    KeyForwardDelete = 0x75,
+    KeyF4 = 0x76,
+    KeyEnd = 0x77,
+    KeyF2 = 0x78,
+    KeyPageDown = 0x79,
+    KeyF1 = 0x7A,
+    KeyLeftArrow = 0x7B,
+    KeyRightArrow = 0x7C,
+    KeyDownArrow = 0x7D,
+    KeyUpArrow = 0x7E,
+}
+
+impl TryFrom<Linux> for MacOS {
+    type Error = ();
+
+    fn try_from(value: Linux) -> Result<Self, Self::Error> {
+        match value {
+            Linux::KeyA => Ok(Self::KeyA),
+            Linux::KeyS => Ok(Self::KeyS),
+            Linux::KeyD => Ok(Self::KeyD),
+            Linux::KeyF => Ok(Self::KeyF),
+            Linux::KeyH => Ok(Self::KeyH),
+            Linux::KeyG => Ok(Self::KeyG),
+            Linux::KeyZ => Ok(Self::KeyZ),
+            Linux::KeyX => Ok(Self::KeyX),
+            Linux::KeyC => Ok(Self::KeyC),
+            Linux::KeyV => Ok(Self::KeyV),
+            Linux::Key102nd => Ok(Self::KeyIsoSection),
+            Linux::KeyB => Ok(Self::KeyB),
+            Linux::KeyQ => Ok(Self::KeyQ),
+            Linux::KeyW => Ok(Self::KeyW),
+            Linux::KeyE => Ok(Self::KeyE),
+            Linux::KeyR => Ok(Self::KeyR),
+            Linux::KeyY => Ok(Self::KeyY),
+            Linux::KeyT => Ok(Self::KeyT),
+            Linux::Key1 => Ok(Self::Key1),
+            Linux::Key2 => Ok(Self::Key2),
+            Linux::Key3 => Ok(Self::Key3),
+            Linux::Key4 => Ok(Self::Key4),
+            Linux::Key6 => Ok(Self::Key6),
+            Linux::Key5 => Ok(Self::Key5),
+            Linux::KeyEqual => Ok(Self::KeyEqual),
+            Linux::Key9 => Ok(Self::Key9),
+            Linux::Key7 => Ok(Self::Key7),
+            Linux::KeyMinus => Ok(Self::KeyMinus),
+            Linux::Key8 => Ok(Self::Key8),
+            Linux::Key0 => Ok(Self::Key0),
+            Linux::KeyRightbrace => Ok(Self::KeyRightBracket),
+            Linux::KeyO => Ok(Self::KeyO),
+            Linux::KeyU => Ok(Self::KeyU),
+            Linux::KeyLeftbrace => Ok(Self::KeyLeftBracket),
+            Linux::KeyI => Ok(Self::KeyI),
+            Linux::KeyP => Ok(Self::KeyP),
+            Linux::KeyEnter => Ok(Self::KeyReturn),
+            Linux::KeyL => Ok(Self::KeyL),
+            Linux::KeyJ => Ok(Self::KeyJ),
+            Linux::KeyApostrophe => Ok(Self::KeyQuote),
+            Linux::KeyK => Ok(Self::KeyK),
+            Linux::KeySemicolon => Ok(Self::KeySemicolon),
+            Linux::KeyBackslash => Ok(Self::KeyBackslash),
+            Linux::KeyComma => Ok(Self::KeyComma),
+            Linux::KeySlash => Ok(Self::KeySlash),
+            Linux::KeyN => Ok(Self::KeyN),
+            Linux::KeyM => Ok(Self::KeyM),
+            Linux::KeyDot => Ok(Self::KeyPeriod),
+            Linux::KeyTab => Ok(Self::KeyTab),
+            Linux::KeySpace => Ok(Self::KeySpace),
+            Linux::KeyGrave => Ok(Self::KeyGrave),
+            Linux::KeyBackspace => Ok(Self::KeyDelete),
+            Linux::KeyEsc => Ok(Self::KeyEscape),
+            Linux::KeyRightmeta => Ok(Self::KeyRightCommand),
+            Linux::KeyLeftMeta => Ok(Self::KeyCommand),
+            Linux::KeyLeftShift => Ok(Self::KeyShift),
+            Linux::KeyCapsLock => Ok(Self::KeyCapsLock),
+            Linux::KeyLeftAlt => Ok(Self::KeyOption),
+            Linux::KeyLeftCtrl => Ok(Self::KeyControl),
+            Linux::KeyRightShift => Ok(Self::KeyRightShift),
+            Linux::KeyRightalt => Ok(Self::KeyRightOption),
+            Linux::KeyRightCtrl => Ok(Self::KeyRightControl),
+            Linux::KeyF17 => Ok(Self::KeyF17),
+            Linux::KeyKpDot => Ok(Self::KeypadDecimal),
+            Linux::KeyKpAsterisk => Ok(Self::KeypadMultiply),
+            Linux::KeyKpplus => Ok(Self::KeypadPlus),
+            Linux::KeyNumlock => Ok(Self::KeypadClear),
+            Linux::KeyVolumeUp => Ok(Self::KeyVolumeUp),
+            Linux::KeyVolumeDown => Ok(Self::KeyVolumeDown),
+            Linux::KeyMute => Ok(Self::KeyMute),
+            Linux::KeyKpslash => Ok(Self::KeypadDivide),
+            Linux::KeyKpEnter => Ok(Self::KeypadEnter),
+            Linux::KeyKpMinus => Ok(Self::KeypadMinus),
+            Linux::KeyF18 => Ok(Self::KeyF18),
+            Linux::KeyF19 => Ok(Self::KeyF19),
+            Linux::KeyKpequal => Ok(Self::KeypadEquals),
+            Linux::KeyKp0 => Ok(Self::Keypad0),
+            Linux::KeyKp1 => Ok(Self::Keypad1),
+            Linux::KeyKp2 => Ok(Self::Keypad2),
+            Linux::KeyKp3 => Ok(Self::Keypad3),
+            Linux::KeyKp4 => Ok(Self::Keypad4),
+            Linux::KeyKp5 => Ok(Self::Keypad5),
+            Linux::KeyKp6 => Ok(Self::Keypad6),
+            Linux::KeyKp7 => Ok(Self::Keypad7),
+            Linux::KeyF20 => Ok(Self::KeyF20),
+            Linux::KeyKp8 => Ok(Self::Keypad8),
+            Linux::KeyKp9 => Ok(Self::Keypad9),
+            Linux::KeyYen => Ok(Self::KeyJisYen),
+            Linux::KeyRo => Ok(Self::KeyJisUnderscore),
+            Linux::KeyKpJpComma => Ok(Self::KeyJisKeypadComma),
+            Linux::KeyF5 => Ok(Self::KeyF5),
+            Linux::KeyF6 => Ok(Self::KeyF6),
+            Linux::KeyF7 => Ok(Self::KeyF7),
+            Linux::KeyF3 => Ok(Self::KeyF3),
+            Linux::KeyF8 => Ok(Self::KeyF8),
+            Linux::KeyF9 => Ok(Self::KeyF9),
+            Linux::KeyHanja => Ok(Self::KeyJisEisu),
+            Linux::KeyF11 => Ok(Self::KeyF11),
+            Linux::KeyHanguel => Ok(Self::KeyJisKana),
+            Linux::KeyF13 => Ok(Self::KeyF13),
+            Linux::KeyF16 => Ok(Self::KeyF16),
+            Linux::KeyF14 => Ok(Self::KeyF14),
+            Linux::KeyF10 => Ok(Self::KeyF10),
+            Linux::KeyCompose => Ok(Self::KeyContextualMenu),
+            Linux::KeyF12 => Ok(Self::KeyF12),
+            Linux::KeyF15 => Ok(Self::KeyF15),
+            Linux::KeyInsert => Ok(Self::KeyHelp),
+            Linux::KeyHome => Ok(Self::KeyHome),
+            Linux::KeyPageup => Ok(Self::KeyPageUp),
+            Linux::KeyDelete => Ok(Self::KeyForwardDelete),
+            Linux::KeyF4 => Ok(Self::KeyF4),
+            Linux::KeyEnd => Ok(Self::KeyEnd),
+            Linux::KeyF2 => Ok(Self::KeyF2),
+            Linux::KeyPagedown => Ok(Self::KeyPageDown),
+            Linux::KeyF1 => Ok(Self::KeyF1),
+            Linux::KeyLeft => Ok(Self::KeyLeftArrow),
+            Linux::KeyRight => Ok(Self::KeyRightArrow),
+            Linux::KeyDown => Ok(Self::KeyDownArrow),
+            Linux::KeyUp => Ok(Self::KeyUpArrow),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<MacOS> for Linux {
+    fn from(value: MacOS) -> Self {
+        match value {
+            MacOS::KeyA => Self::KeyA,
+            MacOS::KeyS => Self::KeyS,
+            MacOS::KeyD => Self::KeyD,
+            MacOS::KeyF => Self::KeyF,
+            MacOS::KeyH => Self::KeyH,
+            MacOS::KeyG => Self::KeyG,
+            MacOS::KeyZ => Self::KeyZ,
+            MacOS::KeyX => Self::KeyX,
+            MacOS::KeyC => Self::KeyC,
+            MacOS::KeyV => Self::KeyV,
+            MacOS::KeyIsoSection => Self::Key102nd,
+            MacOS::KeyB => Self::KeyB,
+            MacOS::KeyQ => Self::KeyQ,
+            MacOS::KeyW => Self::KeyW,
+            MacOS::KeyE => Self::KeyE,
+            MacOS::KeyR => Self::KeyR,
+            MacOS::KeyY => Self::KeyY,
+            MacOS::KeyT => Self::KeyT,
+            MacOS::Key1 => Self::Key1,
+            MacOS::Key2 => Self::Key2,
+            MacOS::Key3 => Self::Key3,
+            MacOS::Key4 => Self::Key4,
+            MacOS::Key6 => Self::Key6,
+            MacOS::Key5 => Self::Key5,
+            MacOS::KeyEqual => Self::KeyEqual,
+            MacOS::Key9 => Self::Key9,
+            MacOS::Key7 => Self::Key7,
+            MacOS::KeyMinus => Self::KeyMinus,
+            MacOS::Key8 => Self::Key8,
+            MacOS::Key0 => Self::Key0,
+            MacOS::KeyRightBracket => Self::KeyRightbrace,
+            MacOS::KeyO => Self::KeyO,
+            MacOS::KeyU => Self::KeyU,
+            MacOS::KeyLeftBracket => Self::KeyLeftbrace,
+            MacOS::KeyI => Self::KeyI,
+            MacOS::KeyP => Self::KeyP,
+            MacOS::KeyReturn => Self::KeyEnter,
+            MacOS::KeyL => Self::KeyL,
+            MacOS::KeyJ => Self::KeyJ,
+            MacOS::KeyQuote => Self::KeyApostrophe,
+            MacOS::KeyK => Self::KeyK,
+            MacOS::KeySemicolon => Self::KeySemicolon,
+            MacOS::KeyBackslash => Self::KeyBackslash,
+            MacOS::KeyComma => Self::KeyComma,
+            MacOS::KeySlash => Self::KeySlash,
+            MacOS::KeyN => Self::KeyN,
+            MacOS::KeyM => Self::KeyM,
+            MacOS::KeyPeriod => Self::KeyDot,
+            MacOS::KeyTab => Self::KeyTab,
+            MacOS::KeySpace => Self::KeySpace,
+            MacOS::KeyGrave => Self::KeyGrave,
+            MacOS::KeyDelete => Self::KeyBackspace,
+            MacOS::KeyEscape => Self::KeyEsc,
+            MacOS::KeyRightCommand => Self::KeyRightmeta,
+            MacOS::KeyCommand => Self::KeyLeftMeta,
+            MacOS::KeyShift => Self::KeyLeftShift,
+            MacOS::KeyCapsLock => Self::KeyCapsLock,
+            MacOS::KeyOption => Self::KeyLeftAlt,
+            MacOS::KeyControl => Self::KeyLeftCtrl,
+            MacOS::KeyRightShift => Self::KeyRightShift,
+            MacOS::KeyRightOption => Self::KeyRightalt,
+            MacOS::KeyRightControl => Self::KeyRightCtrl,
+            MacOS::KeyF17 => Self::KeyF17,
+            MacOS::KeypadDecimal => Self::KeyKpDot,
+            MacOS::KeypadMultiply => Self::KeyKpAsterisk,
+            MacOS::KeypadPlus => Self::KeyKpplus,
+            MacOS::KeypadClear => Self::KeyNumlock,
+            MacOS::KeyVolumeUp => Self::KeyVolumeUp,
+            MacOS::KeyVolumeDown => Self::KeyVolumeDown,
+            MacOS::KeyMute => Self::KeyMute,
+            MacOS::KeypadDivide => Self::KeyKpslash,
+            MacOS::KeypadEnter => Self::KeyKpEnter,
+            MacOS::KeypadMinus => Self::KeyKpMinus,
+            MacOS::KeyF18 => Self::KeyF18,
+            MacOS::KeyF19 => Self::KeyF19,
+            MacOS::KeypadEquals => Self::KeyKpequal,
+            MacOS::Keypad0 => Self::KeyKp0,
+            MacOS::Keypad1 => Self::KeyKp1,
+            MacOS::Keypad2 => Self::KeyKp2,
+            MacOS::Keypad3 => Self::KeyKp3,
+            MacOS::Keypad4 => Self::KeyKp4,
+            MacOS::Keypad5 => Self::KeyKp5,
+            MacOS::Keypad6 => Self::KeyKp6,
+            MacOS::Keypad7 => Self::KeyKp7,
+            MacOS::KeyF20 => Self::KeyF20,
+            MacOS::Keypad8 => Self::KeyKp8,
+            MacOS::Keypad9 => Self::KeyKp9,
+            MacOS::KeyJisYen => Self::KeyYen,
+            MacOS::KeyJisUnderscore => Self::KeyRo,
+            MacOS::KeyJisKeypadComma => Self::KeyKpJpComma,
+            MacOS::KeyF5 => Self::KeyF5,
+            MacOS::KeyF6 => Self::KeyF6,
+            MacOS::KeyF7 => Self::KeyF7,
+            MacOS::KeyF3 => Self::KeyF3,
+            MacOS::KeyF8 => Self::KeyF8,
+            MacOS::KeyF9 => Self::KeyF9,
+            MacOS::KeyJisEisu => Self::KeyHanja,
+            MacOS::KeyF11 => Self::KeyF11,
+            MacOS::KeyJisKana => Self::KeyHanguel,
+            MacOS::KeyF13 => Self::KeyF13,
+            MacOS::KeyF16 => Self::KeyF16,
+            MacOS::KeyF14 => Self::KeyF14,
+            MacOS::KeyF10 => Self::KeyF10,
+            MacOS::KeyContextualMenu => Self::KeyCompose,
+            MacOS::KeyF12 => Self::KeyF12,
+            MacOS::KeyF15 => Self::KeyF15,
+            MacOS::KeyHelp => Self::KeyInsert,
+            MacOS::KeyHome => Self::KeyHome,
+            MacOS::KeyPageUp => Self::KeyPageup,
+            MacOS::KeyForwardDelete => Self::KeyDelete,
+            MacOS::KeyF4 => Self::KeyF4,
+            MacOS::KeyEnd => Self::KeyEnd,
+            MacOS::KeyF2 => Self::KeyF2,
+            MacOS::KeyPageDown => Self::KeyPagedown,
+            MacOS::KeyF1 => Self::KeyF1,
+            MacOS::KeyLeftArrow => Self::KeyLeft,
+            MacOS::KeyRightArrow => Self::KeyRight,
+            MacOS::KeyDownArrow => Self::KeyDown,
+            MacOS::KeyUpArrow => Self::KeyUp,
+        }
+    }
+}
+
+/*
+ * X11 keycodes of the evdev driver (and of Xwayland) are the evdev ones offset by 8,
+ * like xkb keycodes
+ */
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct X11(pub u32);
+
+const X11_OFFSET: u32 = 8;
+
+impl From<Linux> for X11 {
+    fn from(value: Linux) -> Self {
+        Self(value as u32 + X11_OFFSET)
+    }
+}
+
+impl TryFrom<X11> for Linux {
+    type Error = ();
+
+    fn try_from(value: X11) -> Result<Self, Self::Error> {
+        let code = value.0.checked_sub(X11_OFFSET).ok_or(())?;
+        Self::try_from(code).map_err(|_| ())
+    }
+}
@@ -0,0 +1,295 @@
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::ptr::NonNull;
+use std::sync::OnceLock;
+
+use libloading::Library;
+
+/*
+ * minimal bindings to libxkbcommon, keycodes are xkb keycodes,
+ * i.e. evdev keycodes + 8
+ */
+
+#[repr(C)]
+struct XkbContext {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct XkbKeymap {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct XkbState {
+    _private: [u8; 0],
+}
+
+const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+
+// libxkbcommon is loaded at runtime so building doesn't need its development files,
+// without it keymaps can't be compiled and keysyms have no characters
+const LIBRARY: &str = "libxkbcommon.so.0";
+
+macro_rules! functions {
+    ($($name:ident: fn($($arg:ty),*) $(-> $ret:ty)?;)*) => {
+        struct Functions {
+            // keeps the function pointers valid
+            _library: Library,
+            $($name: unsafe extern "C" fn($($arg),*) $(-> $ret)?,)*
+        }
+
+        impl Functions {
+            fn load() -> Result<Self, libloading::Error> {
+                unsafe {
+                    let library = Library::new(LIBRARY)?;
+                    Ok(Self {
+                        $($name: *library.get(concat!(stringify!($name), "\0").as_bytes())?,)*
+                        _library: library,
+                    })
+                }
+            }
+        }
+    };
+}
+
+functions! {
+    xkb_context_new: fn(c_int) -> *mut XkbContext;
+    xkb_context_unref: fn(*mut XkbContext);
+
+    xkb_keymap_new_from_string: fn(*mut XkbContext, *const c_char, c_int, c_int) -> *mut XkbKeymap;
+    xkb_keymap_unref: fn(*mut XkbKeymap);
+    xkb_keymap_get_as_string: fn(*mut XkbKeymap, c_int) -> *mut c_char;
+    xkb_keymap_min_keycode: fn(*mut XkbKeymap) -> u32;
+    xkb_keymap_max_keycode: fn(*mut XkbKeymap) -> u32;
+    xkb_keymap_key_get_name: fn(*mut XkbKeymap, u32) -> *const c_char;
+    xkb_keymap_num_layouts_for_key: fn(*mut XkbKeymap, u32) -> u32;
+    xkb_keymap_num_levels_for_key: fn(*mut XkbKeymap, u32, u32) -> u32;
+    xkb_keymap_key_get_syms_by_level: fn(*mut XkbKeymap, u32, u32, u32, *mut *const u32) -> c_int;
+    xkb_keymap_key_get_mods_for_level: fn(*mut XkbKeymap, u32, u32, u32, *mut u32, usize) -> usize;
+
+    xkb_state_new: fn(*mut XkbKeymap) -> *mut XkbState;
+    xkb_state_unref: fn(*mut XkbState);
+    xkb_state_update_mask: fn(*mut XkbState, u32, u32, u32, u32, u32, u32) -> c_int;
+    xkb_state_key_get_utf32: fn(*mut XkbState, u32) -> u32;
+
+    xkb_keysym_to_utf32: fn(u32) -> u32;
+    xkb_keysym_get_name: fn(u32, *mut c_char, usize) -> c_int;
+}
+
+fn functions() -> Option<&'static Functions> {
+    static FUNCTIONS: OnceLock<Option<Functions>> = OnceLock::new();
+    FUNCTIONS
+        .get_or_init(|| {
+            Functions::load()
+                .inspect_err(|e| eprintln!("failed to load {LIBRARY}: {e}"))
+                .ok()
+        })
+        .as_ref()
+}
+
+unsafe extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
+/// A compiled XKB keymap.
+pub struct Keymap {
+    keymap: NonNull<XkbKeymap>,
+    xkb: &'static Functions,
+}
+
+// keymaps are immutable and reference counted atomically
+unsafe impl Send for Keymap {}
+
+impl Keymap {
+    /// Compiles a keymap in the XKB text format.
+    pub fn new(text: &str) -> Option<Self> {
+        let xkb = functions()?;
+        let text = CString::new(text).ok()?;
+        unsafe {
+            let context = (xkb.xkb_context_new)(0);
+            if context.is_null() {
+                return None;
+            }
+            let keymap = (xkb.xkb_keymap_new_from_string)(
+                context,
+                text.as_ptr(),
+                XKB_KEYMAP_FORMAT_TEXT_V1,
+                0,
+            );
+            // the keymap holds its own reference
+            (xkb.xkb_context_unref)(context);
+            NonNull::new(keymap).map(|keymap| Self { keymap, xkb })
+        }
+    }
+
+    pub fn to_text(&self) -> Option<String> {
+        unsafe {
+            let text = (self.xkb.xkb_keymap_get_as_string)(
+                self.keymap.as_ptr(),
+                XKB_KEYMAP_FORMAT_TEXT_V1,
+            );
+            if text.is_null() {
+                return None;
+            }
+            let result = CStr::from_ptr(text).to_string_lossy().into_owned();
+            free(text.cast());
+            Some(result)
+        }
+    }
+
+    fn keycodes(&self) -> impl Iterator<Item = u32> + '_ {
+        let (min, max) = unsafe {
+            (
+                (self.xkb.xkb_keymap_min_keycode)(self.keymap.as_ptr()),
+                (self.xkb.xkb_keymap_max_keycode)(self.keymap.as_ptr()),
+            )
+        };
+        min..=max
+    }
+
+    pub fn key_name(&self, keycode: u32) -> Option<String> {
+        unsafe {
+            let name = (self.xkb.xkb_keymap_key_get_name)(self.keymap.as_ptr(), keycode);
+            (!name.is_null()).then(|| CStr::from_ptr(name).to_string_lossy().into_owned())
+        }
+    }
+
+    /// A keycode and the modifiers producing `c` in `layout`, if any.
+    pub fn find(&self, c: char, layout: u32) -> Option<(u32, u32)> {
+        let keymap = self.keymap.as_ptr();
+        let xkb = self.xkb;
+        for keycode in self.keycodes() {
+            let levels = unsafe { (xkb.xkb_keymap_num_levels_for_key)(keymap, keycode, layout) };
+            for level in 0..levels {
+                let mut syms = std::ptr::null();
+                let n = unsafe {
+                    (xkb.xkb_keymap_key_get_syms_by_level)(
+                        keymap, keycode, layout, level, &mut syms,
+                    )
+                };
+                if n <= 0 {
+                    continue;
+                }
+                let syms = unsafe { std::slice::from_raw_parts(syms, n as usize) };
+                if !syms.iter().any(|&sym| keysym_to_char(sym) == Some(c)) {
+                    continue;
+                }
+
+                let mut masks = [0; 4];
+                let n = unsafe {
+                    (xkb.xkb_keymap_key_get_mods_for_level)(
+                        keymap,
+                        keycode,
+                        layout,
+                        level,
+                        masks.as_mut_ptr(),
+                        masks.len(),
+                    )
+                };
+                if n > 0 {
+                    return Some((keycode, masks[0]));
+                }
+            }
+        }
+        None
+    }
+
+    /// Keycodes that have a name but no symbols, they can be bound to anything.
+    pub fn unbound_keycodes(&self) -> Vec<u32> {
+        self.keycodes()
+            .filter(|&keycode| {
+                self.key_name(keycode).is_some()
+                    && unsafe {
+                        (self.xkb.xkb_keymap_num_layouts_for_key)(self.keymap.as_ptr(), keycode)
+                    } == 0
+            })
+            .collect()
+    }
+
+    /// This keymap in the XKB text format, with each `(keycode, keysym)` of `bindings`
+    /// bound on all layouts. The keycodes should be taken from `unbound_keycodes`.
+    pub fn bind_keys(&self, bindings: &[(u32, u32)]) -> Option<String> {
+        let mut text = self.to_text()?;
+
+        let mut keys = String::new();
+        for &(keycode, keysym) in bindings {
+            let name = self.key_name(keycode)?;
+            let sym = keysym_name(keysym)?;
+            keys.push_str(&format!("\n\tkey <{name}> {{ [ {sym} ] }};"));
+        }
+
+        let section = text.find("xkb_symbols")?;
+        let body = section + text[section..].find('{')? + 1;
+        text.insert_str(body, &keys);
+        Some(text)
+    }
+}
+
+impl Drop for Keymap {
+    fn drop(&mut self) {
+        unsafe { (self.xkb.xkb_keymap_unref)(self.keymap.as_ptr()) };
+    }
+}
+
+/// Modifiers and layout applied to a keymap.
+pub struct State {
+    state: NonNull<XkbState>,
+    xkb: &'static Functions,
+}
+
+unsafe impl Send for State {}
+
+impl State {
+    pub fn new(keymap: &Keymap) -> Option<Self> {
+        let xkb = keymap.xkb;
+        // the state holds its own reference to the keymap
+        NonNull::new(unsafe { (xkb.xkb_state_new)(keymap.keymap.as_ptr()) })
+            .map(|state| Self { state, xkb })
+    }
+
+    pub fn update_mask(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        unsafe {
+            (self.xkb.xkb_state_update_mask)(
+                self.state.as_ptr(),
+                depressed,
+                latched,
+                locked,
+                0,
+                0,
+                group,
+            )
+        };
+    }
+
+    /// The character `keycode` produces in the current state.
+    pub fn key_char(&self, keycode: u32) -> Option<char> {
+        match unsafe { (self.xkb.xkb_state_key_get_utf32)(self.state.as_ptr(), keycode) } {
+            0 => None,
+            c => char::from_u32(c),
+        }
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        unsafe { (self.xkb.xkb_state_unref)(self.state.as_ptr()) };
+    }
+}
+
+pub fn keysym_to_char(keysym: u32) -> Option<char> {
+    match unsafe { (functions()?.xkb_keysym_to_utf32)(keysym) } {
+        0 => None,
+        c => char::from_u32(c),
+    }
+}
+
+pub fn keysym_name(keysym: u32) -> Option<String> {
+    let xkb = functions()?;
+    let mut buf = [0 as c_char; 64];
+    let n = unsafe { (xkb.xkb_keysym_get_name)(keysym, buf.as_mut_ptr(), buf.len()) };
+    if n <= 0 {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
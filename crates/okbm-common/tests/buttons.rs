@@ -0,0 +1,28 @@
+use okbm_common::{BTN_EXTRA, BTN_LEFT, BTN_SIDE, BUTTONS};
+
+#[test]
+fn macos_button_round_trip() {
+    for (number, &button) in BUTTONS.iter().enumerate() {
+        assert_eq!(
+            BUTTONS.iter().position(|&b| b == button),
+            Some(number),
+            "{button:#x}"
+        );
+    }
+}
+
+#[test]
+fn thumb_buttons() {
+    // back and forward on macOS, what Linux mice report for their thumb buttons
+    assert_eq!(BUTTONS[3], BTN_SIDE);
+    assert_eq!(BUTTONS[4], BTN_EXTRA);
+}
+
+#[test]
+fn evdev_buttons_are_contiguous() {
+    let mut buttons = BUTTONS;
+    buttons.sort();
+    for (i, &button) in buttons.iter().enumerate() {
+        assert_eq!(button, BTN_LEFT + i as u32);
+    }
+}
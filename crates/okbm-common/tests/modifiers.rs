@@ -0,0 +1,67 @@
+use okbm_common::scancode::Linux;
+use okbm_common::{ModifierKeys, Modifiers, XMods};
+
+// device dependent and independent bits of CGEventFlags
+const CG_LEFT_SHIFT: u64 = 0x0002_0002;
+const CG_RIGHT_ALT: u64 = 0x0008_0040;
+const CG_CAPS_LOCK: u64 = 0x0001_0000;
+const CG_COMMAND: u64 = 0x0010_0000;
+
+#[test]
+fn cg_flags_round_trip() {
+    let flags = CG_LEFT_SHIFT | CG_RIGHT_ALT | CG_CAPS_LOCK;
+    let mods = Modifiers::from_cg_flags(flags);
+    assert_eq!(
+        mods.pressed,
+        ModifierKeys::LeftShift | ModifierKeys::RightAlt
+    );
+    assert_eq!(mods.locked, XMods::LockMask);
+    assert_eq!(mods.cg_flags(), flags);
+}
+
+#[test]
+fn cg_flags_without_device_bits_are_on_the_left() {
+    let mods = Modifiers::from_cg_flags(CG_COMMAND);
+    assert_eq!(mods.pressed, ModifierKeys::LeftMeta);
+}
+
+#[test]
+fn right_alt_is_level3() {
+    assert_eq!(ModifierKeys::LeftAlt.to_xmods(), XMods::Mod1Mask);
+    assert_eq!(ModifierKeys::RightAlt.to_xmods(), XMods::Mod5Mask);
+    assert_eq!(
+        (ModifierKeys::RightShift | ModifierKeys::LeftCtrl | ModifierKeys::RightMeta).to_xmods(),
+        XMods::ShiftMask | XMods::ControlMask | XMods::Mod4Mask
+    );
+}
+
+#[test]
+fn xkb_masks_keep_the_held_side() {
+    let mut mods = Modifiers::default();
+    mods.update_key(Linux::KeyRightShift as u32, 1);
+    mods.update_xkb(XMods::ShiftMask.bits(), 0, 0, 0);
+    assert_eq!(mods.pressed, ModifierKeys::RightShift);
+
+    // new modifiers are assumed on the left, except level 3
+    mods.update_xkb((XMods::ControlMask | XMods::Mod5Mask).bits(), 0, 0, 1);
+    assert_eq!(
+        mods.pressed,
+        ModifierKeys::LeftCtrl | ModifierKeys::RightAlt
+    );
+    assert_eq!(mods.group, 1);
+    assert_eq!(
+        mods.xkb_masks(),
+        ((XMods::ControlMask | XMods::Mod5Mask).bits(), 0, 0, 1)
+    );
+}
+
+#[test]
+fn lock_keys_toggle() {
+    let mut mods = Modifiers::default();
+    assert!(mods.update_key(Linux::KeyCapsLock as u32, 1));
+    assert_eq!(mods.locked, XMods::LockMask);
+    assert_eq!(
+        mods.lock_keys_to(XMods::Mod2Mask),
+        [Linux::KeyCapsLock, Linux::KeyNumlock]
+    );
+}
@@ -0,0 +1,102 @@
+use okbm_common::scancode::{Hid, Linux, MacOS, Windows, X11};
+
+fn linux_keys() -> impl Iterator<Item = Linux> {
+    (0..=u16::MAX as u32).filter_map(|code| Linux::try_from(code).ok())
+}
+
+#[test]
+fn hid_round_trip() {
+    for code in 0..=u16::MAX as u32 {
+        let Ok(hid) = Hid::try_from(code) else {
+            continue;
+        };
+        let linux = Linux::from(hid);
+        // non-US # and \ share a key, it maps back to \
+        let expected = match hid {
+            Hid::KeyNonUSHash => Hid::KeyBackslash,
+            hid => hid,
+        };
+        assert_eq!(Hid::try_from(linux), Ok(expected), "{hid:?} -> {linux:?}");
+    }
+}
+
+#[test]
+fn linux_hid_round_trip() {
+    for linux in linux_keys() {
+        if let Ok(hid) = Hid::try_from(linux) {
+            assert_eq!(Linux::from(hid), linux, "{linux:?} -> {hid:?}");
+        }
+    }
+}
+
+#[test]
+fn macos_round_trip() {
+    for code in 0..=u16::MAX {
+        let Ok(mac) = MacOS::try_from(code) else {
+            continue;
+        };
+        let linux = Linux::from(mac);
+        assert_eq!(MacOS::try_from(linux), Ok(mac), "{mac:?} -> {linux:?}");
+    }
+}
+
+#[test]
+fn linux_macos_round_trip() {
+    for linux in linux_keys() {
+        if let Ok(mac) = MacOS::try_from(linux) {
+            assert_eq!(Linux::from(mac), linux, "{linux:?} -> {mac:?}");
+        }
+    }
+}
+
+#[test]
+fn x11_round_trip() {
+    for linux in linux_keys() {
+        let x11 = X11::from(linux);
+        assert_eq!(x11.0, linux as u32 + 8);
+        assert_eq!(Linux::try_from(x11), Ok(linux));
+    }
+    for code in 0..8 {
+        assert_eq!(Linux::try_from(X11(code)), Err(()));
+    }
+}
+
+#[test]
+fn windows_round_trip() {
+    // several linux keys share a windows scancode (0x76 is both F24 and LANG5),
+    // so only check from the windows side
+    for code in 0..=u16::MAX as u32 {
+        let Ok(windows) = Windows::try_from(code) else {
+            continue;
+        };
+        // the shift windows injects around extended keys
+        if matches!(windows, Windows::KeyFakeRightShift) {
+            continue;
+        }
+        if let Ok(linux) = Linux::try_from(windows) {
+            assert_eq!(
+                Windows::try_from(linux),
+                Ok(windows),
+                "{windows:?} -> {linux:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn common_keys_map_everywhere() {
+    for linux in [
+        Linux::KeyA,
+        Linux::KeyEnter,
+        Linux::KeyEsc,
+        Linux::KeyLeftShift,
+        Linux::KeyLeftMeta,
+        Linux::KeyUp,
+        Linux::KeyF12,
+        Linux::KeyKpEnter,
+    ] {
+        assert!(Hid::try_from(linux).is_ok(), "{linux:?}");
+        assert!(MacOS::try_from(linux).is_ok(), "{linux:?}");
+        assert!(Windows::try_from(linux).is_ok(), "{linux:?}");
+    }
+}
@@ -0,0 +1,85 @@
+#![cfg(all(unix, not(target_os = "macos")))]
+
+use okbm_common::xkb::{Keymap, State, keysym_name, keysym_to_char};
+
+// self-contained so the tests don't depend on the installed xkeyboard-config
+const KEYMAP: &str = r#"xkb_keymap {
+    xkb_keycodes "test" {
+        minimum = 8;
+        maximum = 255;
+        <AC01> = 38;
+        <AC02> = 39;
+        <LFSH> = 50;
+        <FK13> = 191;
+        <FK14> = 192;
+    };
+    xkb_types "test" {
+        type "ONE_LEVEL" {
+            modifiers = none;
+            level_name[Level1] = "Any";
+        };
+        type "ALPHABETIC" {
+            modifiers = Shift+Lock;
+            map[Shift] = Level2;
+            map[Lock] = Level2;
+            level_name[Level1] = "Base";
+            level_name[Level2] = "Caps";
+        };
+    };
+    xkb_compatibility "test" {
+    };
+    xkb_symbols "test" {
+        key <AC01> { type = "ALPHABETIC", [ a, A ] };
+        key <AC02> { type = "ALPHABETIC", [ s, S ] };
+        key <LFSH> { [ Shift_L ] };
+        modifier_map Shift { <LFSH> };
+    };
+};
+"#;
+
+const SHIFT: u32 = 1;
+const XKB_KEY_EACUTE: u32 = 0xe9;
+
+fn keymap() -> Keymap {
+    Keymap::new(KEYMAP).expect("compile the test keymap")
+}
+
+#[test]
+fn find_characters() {
+    let keymap = keymap();
+    assert_eq!(keymap.find('a', 0), Some((38, 0)));
+    assert_eq!(keymap.find('S', 0), Some((39, SHIFT)));
+    assert_eq!(keymap.find('é', 0), None);
+}
+
+#[test]
+fn unbound_keycodes_have_names_but_no_symbols() {
+    assert_eq!(keymap().unbound_keycodes(), [191, 192]);
+}
+
+#[test]
+fn bound_keys_produce_their_keysym() {
+    let keymap = keymap();
+    let text = keymap.bind_keys(&[(191, XKB_KEY_EACUTE)]).unwrap();
+    let bound = Keymap::new(&text).expect("compile the bound keymap");
+
+    assert_eq!(bound.find('é', 0), Some((191, 0)));
+    assert_eq!(bound.unbound_keycodes(), [192]);
+    // the rest of the keymap is unchanged
+    assert_eq!(bound.find('A', 0), Some((38, SHIFT)));
+}
+
+#[test]
+fn state_applies_modifiers() {
+    let keymap = keymap();
+    let mut state = State::new(&keymap).unwrap();
+    assert_eq!(state.key_char(38), Some('a'));
+    state.update_mask(SHIFT, 0, 0, 0);
+    assert_eq!(state.key_char(38), Some('A'));
+}
+
+#[test]
+fn keysyms() {
+    assert_eq!(keysym_to_char(XKB_KEY_EACUTE), Some('é'));
+    assert_eq!(keysym_name(XKB_KEY_EACUTE).as_deref(), Some("eacute"));
+}
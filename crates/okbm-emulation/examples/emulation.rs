@@ -16,6 +16,9 @@ async fn main() -> Result<()> {
                 0,
             )
             .await?;
+        emulation
+            .consume(Event::Pointer(PointerEvent::Frame), 0)
+            .await?;
 
         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
 
@@ -29,6 +32,9 @@ async fn main() -> Result<()> {
                 0,
             )
             .await?;
+        emulation
+            .consume(Event::Pointer(PointerEvent::Frame), 0)
+            .await?;
 
         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
     }
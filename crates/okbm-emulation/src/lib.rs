@@ -10,10 +10,32 @@ mod wayland;
 pub(crate) use wayland::*;
 
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub use eyre::Result;
 pub use okbm_common::*;
 
+/// What went wrong in the backend while emulating the events of a peer.
+#[derive(Debug)]
+pub enum EmulationError {
+    // an event the backend has no equivalent for
+    InvalidEvent(Event),
+    // the compositor closed the connection, nothing is emulated anymore
+    Protocol(String),
+}
+
+impl fmt::Display for EmulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmulationError::InvalidEvent(event) => write!(f, "failed to convert event: {event:?}"),
+            EmulationError::Protocol(e) => write!(f, "protocol violation: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for EmulationError {}
+
 pub(crate) enum EmulationKind {
     #[cfg(target_os = "macos")]
     MacOS(MacOSEmulation),
@@ -22,16 +44,108 @@ pub(crate) enum EmulationKind {
 }
 
 impl EmulationKind {
-    pub async fn consume(
+    pub async fn consume(&mut self, event: Event, handle: u32) -> Result<()> {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(emulation) => emulation.consume(event, handle).await,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.consume(event, handle).await,
+        }
+    }
+
+    pub fn dropped(&self, #[allow(unused_variables)] handle: u32) -> u64 {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(_) => 0,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.dropped(handle),
+        }
+    }
+
+    pub fn set_motion_coalescing(&mut self, #[allow(unused_variables)] enabled: bool) {
+        match self {
+            // posting a CGEvent never blocks, there is no backpressure to handle
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(_) => {}
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.set_motion_coalescing(enabled),
+        }
+    }
+
+    pub fn has_pending(&self) -> bool {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(_) => false,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.has_pending(),
+        }
+    }
+
+    pub fn flush_pending(&mut self) -> Result<()> {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(_) => Ok(()),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.flush_pending(),
+        }
+    }
+
+    pub fn set_keymap(
         &mut self,
-        event: Event,
         #[allow(unused_variables)] handle: u32,
+        keymap: &Keymap,
     ) -> Result<()> {
         match self {
             #[cfg(target_os = "macos")]
-            EmulationKind::MacOS(emulation) => emulation.consume(event).await,
+            EmulationKind::MacOS(emulation) => {
+                emulation.set_keymap(keymap);
+                Ok(())
+            }
             #[cfg(all(unix, not(target_os = "macos")))]
-            EmulationKind::Wayland(emulation) => emulation.consume(event, handle).await,
+            EmulationKind::Wayland(emulation) => emulation.set_keymap(handle, keymap),
+        }
+    }
+
+    pub fn applies_repeat(&self) -> bool {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(_) => true,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(_) => false,
+        }
+    }
+
+    pub fn set_repeat(&mut self, #[allow(unused_variables)] handle: u32, repeat: RepeatInfo) {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(emulation) => emulation.set_repeat(repeat),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.set_repeat(handle, repeat),
+        }
+    }
+
+    pub fn set_lock_sync(&mut self, lock_sync: LockSync) {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(emulation) => emulation.set_lock_sync(lock_sync),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.set_lock_sync(lock_sync),
+        }
+    }
+
+    pub fn sync_locks(
+        &mut self,
+        #[allow(unused_variables)] handle: u32,
+        locks: XMods,
+    ) -> Result<()> {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(emulation) => {
+                emulation.sync_locks(locks);
+                Ok(())
+            }
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.sync_locks(handle, locks),
         }
     }
 
@@ -43,14 +157,23 @@ impl EmulationKind {
             EmulationKind::Wayland(emulation) => emulation.create(handle).await,
         }
     }
+
+    pub async fn destroy(&mut self, handle: u32) -> Result<()> {
+        match self {
+            #[cfg(target_os = "macos")]
+            EmulationKind::MacOS(emulation) => emulation.destroy(handle).await,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            EmulationKind::Wayland(emulation) => emulation.destroy(handle),
+        }
+    }
 }
 
 pub struct Emulation {
     emulation: EmulationKind,
 
-    #[allow(dead_code)]
     handles: HashSet<u32>,
     pressed_keys: HashMap<u32, HashSet<u32>>,
+    lock_sync: LockSync,
 }
 
 impl Emulation {
@@ -67,6 +190,7 @@ impl Emulation {
             },
             handles: Default::default(),
             pressed_keys: Default::default(),
+            lock_sync: Default::default(),
         })
     }
 
@@ -92,6 +216,83 @@ impl Emulation {
         }
     }
 
+    /// Releases the keys `handle` holds and removes its virtual devices,
+    /// which are removed even if releasing some keys fails.
+    pub async fn destroy(&mut self, handle: u32) -> Result<()> {
+        if !self.handles.remove(&handle) {
+            return Ok(());
+        }
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u32;
+        for key in self.pressed_keys.remove(&handle).unwrap_or_default() {
+            let event = Event::Keyboard(KeyboardEvent::Key {
+                time,
+                key,
+                state: 0,
+            });
+            if let Err(e) = self.emulation.consume(event, handle).await {
+                eprintln!("failed to release key {key} of {handle}: {e}");
+            }
+        }
+        self.emulation.destroy(handle).await
+    }
+
+    /// Sum pointer motion instead of discarding it when the backend can't keep up.
+    pub fn set_motion_coalescing(&mut self, enabled: bool) {
+        self.emulation.set_motion_coalescing(enabled);
+    }
+
+    /// Whether motion summed while the backend couldn't keep up is waiting to be sent.
+    pub fn has_pending(&self) -> bool {
+        self.emulation.has_pending()
+    }
+
+    /// Sends the pending motion if the backend has caught up, to be called
+    /// periodically while `has_pending`.
+    pub fn flush_pending(&mut self) -> Result<()> {
+        self.emulation.flush_pending()
+    }
+
+    /// Number of events the backend had to discard for `handle` because
+    /// it could not keep up.
+    pub fn dropped(&self, handle: u32) -> u64 {
+        self.emulation.dropped(handle)
+    }
+
+    /// Installs the keymap `handle`'s peer sent, the local keymap is used until then.
+    pub fn set_keymap(&mut self, handle: u32, keymap: &Keymap) -> Result<()> {
+        self.emulation.set_keymap(handle, keymap)
+    }
+
+    /// Whether `set_repeat` has an effect. On Wayland clients repeat keys themselves
+    /// at the rate of the compositor, a virtual keyboard can't change it.
+    pub fn applies_repeat(&self) -> bool {
+        self.emulation.applies_repeat()
+    }
+
+    /// Key repeat for the keys of `handle`'s peer, see `applies_repeat`.
+    pub fn set_repeat(&mut self, handle: u32, repeat: RepeatInfo) {
+        self.emulation.set_repeat(handle, repeat);
+    }
+
+    /// Whose lock state applies, see `sync_locks`.
+    pub fn set_lock_sync(&mut self, lock_sync: LockSync) {
+        self.lock_sync = lock_sync;
+        self.emulation.set_lock_sync(lock_sync);
+    }
+
+    /// Toggles Caps/Num/Scroll Lock to match the xkb mask `locks` of `handle`'s peer,
+    /// unless the receiver's locks win.
+    pub fn sync_locks(&mut self, handle: u32, locks: u32) -> Result<()> {
+        if self.lock_sync == LockSync::Receiver {
+            return Ok(());
+        }
+        let locks = XMods::from_bits_truncate(locks) & XMods::LOCKS;
+        self.emulation.sync_locks(handle, locks)
+    }
+
     pub async fn consume(&mut self, event: Event, handle: u32) -> Result<()> {
         match event {
             Event::Keyboard(KeyboardEvent::Key { key, state, .. }) => {
@@ -2,61 +2,56 @@ use crate::*;
 
 use eyre::Report;
 
-use bitflags::bitflags;
-
 use core_graphics::base::CGFloat;
 use core_graphics::display::{
     CGDirectDisplayID, CGDisplayBounds, CGGetDisplaysWithRect, CGPoint, CGRect, CGSize,
 };
 use core_graphics::event::{
-    CGEvent, CGEventFlags, CGEventTapLocation, CGEventType, CGKeyCode, CGMouseButton, EventField,
-    ScrollEventUnit,
+    CGEvent, CGEventField, CGEventFlags, CGEventTapLocation, CGEventType, CGKeyCode, CGMouseButton,
+    EventField, ScrollEventUnit,
 };
 use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
-use keycode::{KeyMap, KeyMapping};
 use std::cell::Cell;
-use std::ops::{Index, IndexMut};
+use std::ffi::{c_char, c_void};
 use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::{sync::Notify, task::JoinHandle};
 
-const DEFAULT_REPEAT_DELAY: Duration = Duration::from_millis(500);
-const DEFAULT_REPEAT_INTERVAL: Duration = Duration::from_millis(32);
+// missing from core_graphics::event::EventField
+const SCROLL_WHEEL_EVENT_SCROLL_PHASE: CGEventField = 99;
+
+// CGScrollPhase
+const SCROLL_PHASE_BEGAN: i64 = 1;
+const SCROLL_PHASE_CHANGED: i64 = 2;
+const SCROLL_PHASE_ENDED: i64 = 4;
 
 pub(crate) struct MacOSEmulation {
     event_source: CGEventSource,
     repeat_task: Option<JoinHandle<()>>,
+    // the handle the repeating key was pressed by
+    repeat_handle: Option<u32>,
+    repeat: RepeatInfo,
     button_state: ButtonState,
-    modifier_state: Rc<Cell<XMods>>,
+    // source of the axis events of the current frame
+    axis_source: AxisSource,
+    // a touchpad scroll gesture is in progress
+    scrolling: bool,
+    modifier_state: Rc<Cell<Modifiers>>,
+    lock_sync: LockSync,
+    // toggles the system's caps lock, without it only our events carry the peer's
+    hid_system: Option<HidSystem>,
     notify_repeat_task: Arc<Notify>,
 }
 
-struct ButtonState {
-    left: bool,
-    right: bool,
-    center: bool,
-}
-
-impl Index<CGMouseButton> for ButtonState {
-    type Output = bool;
-
-    fn index(&self, index: CGMouseButton) -> &Self::Output {
-        match index {
-            CGMouseButton::Left => &self.left,
-            CGMouseButton::Right => &self.right,
-            CGMouseButton::Center => &self.center,
-        }
-    }
-}
+// the handle holding each pressed button, indexed by their macOS button number
+#[derive(Default)]
+struct ButtonState([Option<u32>; BUTTONS.len()]);
 
-impl IndexMut<CGMouseButton> for ButtonState {
-    fn index_mut(&mut self, index: CGMouseButton) -> &mut Self::Output {
-        match index {
-            CGMouseButton::Left => &mut self.left,
-            CGMouseButton::Right => &mut self.right,
-            CGMouseButton::Center => &mut self.center,
-        }
+impl ButtonState {
+    // the button a drag is reported for, left and right take precedence
+    fn dragged(&self) -> Option<usize> {
+        self.0.iter().position(Option::is_some)
     }
 }
 
@@ -66,49 +61,123 @@ impl MacOSEmulation {
     pub(crate) fn new() -> Result<Self> {
         let event_source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
             .map_err(|_| Report::msg("error"))?;
-        let button_state = ButtonState {
-            left: false,
-            right: false,
-            center: false,
-        };
         Ok(Self {
             event_source,
-            button_state,
+            button_state: ButtonState::default(),
+            axis_source: AxisSource::Wheel,
+            scrolling: false,
             repeat_task: None,
+            repeat_handle: None,
+            repeat: RepeatInfo::default(),
             notify_repeat_task: Arc::new(Notify::new()),
-            modifier_state: Rc::new(Cell::new(XMods::empty())),
+            modifier_state: Rc::new(Cell::new(Modifiers::default())),
+            lock_sync: LockSync::default(),
+            hid_system: HidSystem::open(),
         })
     }
 
+    // keys are posted as macOS key codes, the local layout applies
+    pub(crate) fn set_keymap(&mut self, _keymap: &Keymap) {}
+
+    pub(crate) fn set_repeat(&mut self, repeat: RepeatInfo) {
+        self.repeat = repeat;
+    }
+
+    // one set of buttons and one repeating key are shared by all peers,
+    // what `handle` still holds of them is released
+    pub(crate) async fn destroy(&mut self, handle: u32) -> Result<()> {
+        if self.repeat_handle == Some(handle) {
+            self.cancel_repeat_task().await;
+        }
+        let held: Vec<u32> = BUTTONS
+            .iter()
+            .zip(self.button_state.0)
+            .filter_map(|(&button, holder)| (holder == Some(handle)).then_some(button))
+            .collect();
+        for button in held {
+            let event = Event::Pointer(PointerEvent::Button {
+                time: 0,
+                button,
+                state: 0,
+            });
+            if let Err(e) = self.consume(event, handle).await {
+                eprintln!("failed to release button {button} of {handle}: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_lock_sync(&mut self, lock_sync: LockSync) {
+        self.lock_sync = lock_sync;
+    }
+
+    // caps lock is the only lock on macOS and posting key events doesn't toggle it,
+    // it is set on the HID system like the key would, LED included. Without access
+    // to it the lock only applies through the flags of the events we post
+    pub(crate) fn sync_locks(&mut self, locked: XMods) {
+        let caps_lock = self.set_caps_lock(locked.contains(XMods::LockMask));
+        let mut mods = self.modifier_state.get();
+        let locked = if caps_lock {
+            XMods::LockMask
+        } else {
+            XMods::empty()
+        };
+        if mods.lock_keys_to(locked).is_empty() {
+            return;
+        }
+        mods.locked = (mods.locked - XMods::LOCKS) | locked;
+        self.modifier_state.set(mods);
+        modifier_event(self.event_source.clone(), mods);
+    }
+
+    // whether caps lock is on once set to `caps_lock`
+    fn set_caps_lock(&self, caps_lock: bool) -> bool {
+        let Some(hid_system) = &self.hid_system else {
+            return caps_lock;
+        };
+        if hid_system.caps_lock() != Some(caps_lock)
+            && let Err(e) = hid_system.set_caps_lock(caps_lock)
+        {
+            eprintln!("failed to set caps lock: {e}");
+        }
+        hid_system.caps_lock().unwrap_or(caps_lock)
+    }
+
     fn get_mouse_location(&self) -> Option<CGPoint> {
         let event: CGEvent = CGEvent::new(self.event_source.clone()).ok()?;
         Some(event.location())
     }
 
-    async fn spawn_repeat_task(&mut self, key: u16) {
+    async fn spawn_repeat_task(&mut self, key: u16, handle: u32) {
         // there can only be one repeating key and it's
         // always the last to be pressed
         self.cancel_repeat_task().await;
+        self.repeat_handle = Some(handle);
         let event_source = self.event_source.clone();
         let notify = self.notify_repeat_task.clone();
         let modifiers = self.modifier_state.clone();
+        let delay = Duration::from_millis(self.repeat.delay as u64);
+        // a rate of 0 disables repeat
+        let interval = (self.repeat.rate > 0).then(|| Duration::from_secs(1) / self.repeat.rate);
         let local = tokio::task::LocalSet::new();
 
         let repeat_task = local
             .run_until(async move {
                 tokio::task::spawn_local(async move {
                     let stop = tokio::select! {
-                        _ = tokio::time::sleep(DEFAULT_REPEAT_DELAY) => false,
+                        _ = tokio::time::sleep(delay) => false,
                         _ = notify.notified() => true,
                     };
-                    if !stop {
-                        loop {
+                    match interval {
+                        Some(interval) if !stop => loop {
                             key_event(event_source.clone(), key, 1, modifiers.get());
                             tokio::select! {
-                                _ = tokio::time::sleep(DEFAULT_REPEAT_INTERVAL) => {},
+                                _ = tokio::time::sleep(interval) => {},
                                 _ = notify.notified() => break,
                             }
-                        }
+                        },
+                        None if !stop => notify.notified().await,
+                        _ => {}
                     }
                     // release key when cancelled
                     update_modifiers(&modifiers, key as u32, 0);
@@ -120,6 +189,7 @@ impl MacOSEmulation {
         self.repeat_task = Some(repeat_task);
     }
     async fn cancel_repeat_task(&mut self) {
+        self.repeat_handle = None;
         if let Some(task) = self.repeat_task.take() {
             self.notify_repeat_task.notify_waiters();
             let _ = task.await;
@@ -127,7 +197,7 @@ impl MacOSEmulation {
     }
 }
 
-fn key_event(event_source: CGEventSource, key: u16, state: u8, modifiers: XMods) {
+fn key_event(event_source: CGEventSource, key: u16, state: u8, modifiers: Modifiers) {
     let event = match CGEvent::new_keyboard_event(event_source, key, state != 0) {
         Ok(e) => e,
         Err(_) => {
@@ -135,21 +205,38 @@ fn key_event(event_source: CGEventSource, key: u16, state: u8, modifiers: XMods)
             return;
         }
     };
-    event.set_flags(to_cgevent_flags(modifiers));
+    event.set_flags(CGEventFlags::from_bits_retain(modifiers.cg_flags()));
     event.post(CGEventTapLocation::HID);
     println!("key event: {key} {state}");
 }
 
-fn modifier_event(event_source: CGEventSource, depressed: XMods) {
+// types `c` whatever the local layout is
+fn unicode_event(event_source: CGEventSource, c: char, state: u8, mut modifiers: Modifiers) {
+    let event = match CGEvent::new_keyboard_event(event_source, 0, state != 0) {
+        Ok(e) => e,
+        Err(_) => {
+            println!("unable to create key event");
+            return;
+        }
+    };
+    event.set_string(c.encode_utf8(&mut [0; 4]));
+    // shift and caps lock are already applied to `c`
+    modifiers.pressed -= ModifierKeys::Shift;
+    modifiers.locked -= XMods::LockMask;
+    event.set_flags(CGEventFlags::from_bits_retain(modifiers.cg_flags()));
+    event.post(CGEventTapLocation::HID);
+    println!("unicode event: {c:?} {state}");
+}
+
+fn modifier_event(event_source: CGEventSource, modifiers: Modifiers) {
     let Ok(event) = CGEvent::new(event_source) else {
         println!("could not create CGEvent");
         return;
     };
-    let flags = to_cgevent_flags(depressed);
     event.set_type(CGEventType::FlagsChanged);
-    event.set_flags(flags);
+    event.set_flags(CGEventFlags::from_bits_retain(modifiers.cg_flags()));
     event.post(CGEventTapLocation::HID);
-    println!("modifiers updated: {depressed:?}");
+    println!("modifiers updated: {modifiers:?}");
 }
 
 fn get_display_at_point(x: CGFloat, y: CGFloat) -> Option<CGDirectDisplayID> {
@@ -224,7 +311,7 @@ fn clamp_to_screen_space(
 }
 
 impl MacOSEmulation {
-    pub async fn consume(&mut self, event: Event) -> Result<()> {
+    pub async fn consume(&mut self, event: Event, handle: u32) -> Result<()> {
         match event {
             Event::Pointer(pointer_event) => match pointer_event {
                 PointerEvent::Motion { time: _, dx, dy } => {
@@ -242,13 +329,12 @@ impl MacOSEmulation {
                     mouse_location.x = new_mouse_x;
                     mouse_location.y = new_mouse_y;
 
-                    let mut event_type = CGEventType::MouseMoved;
-                    if self.button_state.left {
-                        event_type = CGEventType::LeftMouseDragged
-                    } else if self.button_state.right {
-                        event_type = CGEventType::RightMouseDragged
-                    } else if self.button_state.center {
-                        event_type = CGEventType::OtherMouseDragged
+                    let dragged = self.button_state.dragged();
+                    let event_type = match dragged {
+                        None => CGEventType::MouseMoved,
+                        Some(0) => CGEventType::LeftMouseDragged,
+                        Some(1) => CGEventType::RightMouseDragged,
+                        Some(_) => CGEventType::OtherMouseDragged,
                     };
                     let event = match CGEvent::new_mouse_event(
                         self.event_source.clone(),
@@ -262,6 +348,12 @@ impl MacOSEmulation {
                             return Ok(());
                         }
                     };
+                    if let Some(number) = dragged {
+                        event.set_integer_value_field(
+                            EventField::MOUSE_EVENT_BUTTON_NUMBER,
+                            number as i64,
+                        );
+                    }
                     event.set_integer_value_field(EventField::MOUSE_EVENT_DELTA_X, dx as i64);
                     event.set_integer_value_field(EventField::MOUSE_EVENT_DELTA_Y, dy as i64);
                     event.post(CGEventTapLocation::HID);
@@ -271,30 +363,20 @@ impl MacOSEmulation {
                     button,
                     state,
                 } => {
-                    let (event_type, mouse_button) = match (button, state) {
-                        (b, 1) if b == BTN_LEFT => {
-                            (CGEventType::LeftMouseDown, CGMouseButton::Left)
-                        }
-                        (b, 0) if b == BTN_LEFT => (CGEventType::LeftMouseUp, CGMouseButton::Left),
-                        (b, 1) if b == BTN_RIGHT => {
-                            (CGEventType::RightMouseDown, CGMouseButton::Right)
-                        }
-                        (b, 0) if b == BTN_RIGHT => {
-                            (CGEventType::RightMouseUp, CGMouseButton::Right)
-                        }
-                        (b, 1) if b == BTN_MIDDLE => {
-                            (CGEventType::OtherMouseDown, CGMouseButton::Center)
-                        }
-                        (b, 0) if b == BTN_MIDDLE => {
-                            (CGEventType::OtherMouseUp, CGMouseButton::Center)
-                        }
-                        _ => {
-                            println!("invalid button event: {button},{state}");
-                            return Ok(());
-                        }
+                    let Some(number) = BUTTONS.iter().position(|&b| b == button) else {
+                        println!("invalid button event: {button},{state}");
+                        return Ok(());
+                    };
+                    let (event_type, mouse_button) = match (number, state) {
+                        (0, 1) => (CGEventType::LeftMouseDown, CGMouseButton::Left),
+                        (0, _) => (CGEventType::LeftMouseUp, CGMouseButton::Left),
+                        (1, 1) => (CGEventType::RightMouseDown, CGMouseButton::Right),
+                        (1, _) => (CGEventType::RightMouseUp, CGMouseButton::Right),
+                        (_, 1) => (CGEventType::OtherMouseDown, CGMouseButton::Center),
+                        (_, _) => (CGEventType::OtherMouseUp, CGMouseButton::Center),
                     };
                     // store button state
-                    self.button_state[mouse_button] = state == 1;
+                    self.button_state.0[number] = (state == 1).then_some(handle);
 
                     let location = self.get_mouse_location().unwrap();
                     let event = match CGEvent::new_mouse_event(
@@ -309,6 +391,11 @@ impl MacOSEmulation {
                             return Ok(());
                         }
                     };
+                    // CGMouseButton only knows the first three buttons
+                    event.set_integer_value_field(
+                        EventField::MOUSE_EVENT_BUTTON_NUMBER,
+                        number as i64,
+                    );
                     event.post(CGEventTapLocation::HID);
                 }
                 PointerEvent::Axis {
@@ -339,6 +426,21 @@ impl MacOSEmulation {
                             return Ok(());
                         }
                     };
+                    if self.axis_source != AxisSource::Wheel {
+                        event.set_integer_value_field(
+                            EventField::SCROLL_WHEEL_EVENT_IS_CONTINUOUS,
+                            1,
+                        );
+                    }
+                    if self.axis_source == AxisSource::Finger {
+                        let phase = if self.scrolling {
+                            SCROLL_PHASE_CHANGED
+                        } else {
+                            SCROLL_PHASE_BEGAN
+                        };
+                        event.set_integer_value_field(SCROLL_WHEEL_EVENT_SCROLL_PHASE, phase);
+                        self.scrolling = true;
+                    }
                     event.post(CGEventTapLocation::HID);
                 }
                 PointerEvent::AxisDiscrete120 { axis, value } => {
@@ -366,6 +468,38 @@ impl MacOSEmulation {
                     };
                     event.post(CGEventTapLocation::HID);
                 }
+                PointerEvent::AxisSource { source } => {
+                    self.axis_source = source;
+                }
+                PointerEvent::AxisStop { .. } => {
+                    // both axes stop at once, end the gesture only once
+                    if !self.scrolling {
+                        return Ok(());
+                    }
+                    self.scrolling = false;
+                    let event = match CGEvent::new_scroll_event(
+                        self.event_source.clone(),
+                        ScrollEventUnit::PIXEL,
+                        1,
+                        0,
+                        0,
+                        0,
+                    ) {
+                        Ok(e) => e,
+                        Err(()) => {
+                            println!("scroll event creation failed!");
+                            return Ok(());
+                        }
+                    };
+                    event.set_integer_value_field(EventField::SCROLL_WHEEL_EVENT_IS_CONTINUOUS, 1);
+                    event.set_integer_value_field(
+                        SCROLL_WHEEL_EVENT_SCROLL_PHASE,
+                        SCROLL_PHASE_ENDED,
+                    );
+                    event.post(CGEventTapLocation::HID);
+                }
+                // every CGEvent is posted on its own
+                PointerEvent::Frame => {}
             },
             Event::Keyboard(keyboard_event) => match keyboard_event {
                 KeyboardEvent::Key {
@@ -373,17 +507,22 @@ impl MacOSEmulation {
                     key,
                     state,
                 } => {
-                    let code = match KeyMap::from_key_mapping(KeyMapping::Evdev(key as u16)) {
-                        Ok(k) => k.mac as CGKeyCode,
-                        Err(_) => {
+                    let code = match scancode::Linux::try_from(key)
+                        .map_err(|_| ())
+                        .and_then(scancode::MacOS::try_from)
+                    {
+                        Ok(k) => k as CGKeyCode,
+                        Err(()) => {
                             println!("unable to map key event");
                             return Ok(());
                         }
                     };
                     match state {
                         // pressed
-                        1 => self.spawn_repeat_task(code).await,
-                        _ => self.cancel_repeat_task().await,
+                        1 => self.spawn_repeat_task(code, handle).await,
+                        // the key repeating for another peer goes on
+                        _ if self.repeat_handle == Some(handle) => self.cancel_repeat_task().await,
+                        _ => {}
                     }
                     update_modifiers(&self.modifier_state, key, state);
                     key_event(
@@ -399,9 +538,30 @@ impl MacOSEmulation {
                     locked,
                     group,
                 } => {
+                    let locked = match self.lock_sync {
+                        LockSync::Sender => locked,
+                        LockSync::Receiver => local_locks(),
+                    };
                     set_modifiers(&self.modifier_state, depressed, latched, locked, group);
                     modifier_event(self.event_source.clone(), self.modifier_state.get());
+                    if self.lock_sync == LockSync::Sender {
+                        self.set_caps_lock(locked & XMods::LockMask.bits() != 0);
+                    }
                 }
+                KeyboardEvent::Keysym {
+                    time: _,
+                    key: _,
+                    keysym,
+                    state,
+                } => match keysym::to_char(keysym) {
+                    Some(c) => unicode_event(
+                        self.event_source.clone(),
+                        c,
+                        state,
+                        self.modifier_state.get(),
+                    ),
+                    None => println!("unsupported keysym: {keysym:#x}"),
+                },
             },
         }
         // FIXME
@@ -409,80 +569,103 @@ impl MacOSEmulation {
     }
 }
 
-fn update_modifiers(modifiers: &Cell<XMods>, key: u32, state: u8) -> bool {
-    if let Ok(key) = scancode::Linux::try_from(key) {
-        let mask = match key {
-            scancode::Linux::KeyLeftShift | scancode::Linux::KeyRightShift => XMods::ShiftMask,
-            scancode::Linux::KeyCapsLock => XMods::LockMask,
-            scancode::Linux::KeyLeftCtrl | scancode::Linux::KeyRightCtrl => XMods::ControlMask,
-            scancode::Linux::KeyLeftAlt | scancode::Linux::KeyRightalt => XMods::Mod1Mask,
-            scancode::Linux::KeyLeftMeta | scancode::Linux::KeyRightmeta => XMods::Mod4Mask,
-            _ => XMods::empty(),
-        };
-        // unchanged
-        if mask.is_empty() {
-            return false;
+// the locks of the local keyboard, the flags of the events we post don't change them
+fn local_locks() -> u32 {
+    let flags = unsafe { CGEventSourceFlagsState(CGEventSourceStateID::HIDSystemState) };
+    Modifiers::from_cg_flags(flags).locked.bits()
+}
+
+unsafe extern "C" {
+    fn CGEventSourceFlagsState(state_id: CGEventSourceStateID) -> u64;
+}
+
+// connection to the HID system, which owns the state of the lock keys
+struct HidSystem(u32);
+
+// IOKit/hidsystem/IOHIDShared.h
+const IOHID_PARAM_CONNECT_TYPE: u32 = 1;
+const IOHID_CAPS_LOCK_STATE: i32 = 1;
+
+impl HidSystem {
+    fn open() -> Option<Self> {
+        let mut connect = 0;
+        unsafe {
+            // the matching dictionary is consumed, the default main port is 0
+            let service =
+                IOServiceGetMatchingService(0, IOServiceMatching(c"IOHIDSystem".as_ptr()));
+            if service == 0 {
+                println!("no HID system, caps lock won't be toggled");
+                return None;
+            }
+            let result = IOServiceOpen(
+                service,
+                mach_task_self_,
+                IOHID_PARAM_CONNECT_TYPE,
+                &mut connect,
+            );
+            IOObjectRelease(service);
+            if result != 0 {
+                println!("can't open the HID system ({result:#x}), caps lock won't be toggled");
+                return None;
+            }
         }
-        let mut mods = modifiers.get();
-        match state {
-            1 => mods.insert(mask),
-            _ => mods.remove(mask),
+        Some(Self(connect))
+    }
+
+    fn caps_lock(&self) -> Option<bool> {
+        let mut state = false;
+        let result =
+            unsafe { IOHIDGetModifierLockState(self.0, IOHID_CAPS_LOCK_STATE, &mut state) };
+        (result == 0).then_some(state)
+    }
+
+    fn set_caps_lock(&self, state: bool) -> Result<()> {
+        match unsafe { IOHIDSetModifierLockState(self.0, IOHID_CAPS_LOCK_STATE, state) } {
+            0 => Ok(()),
+            result => Err(Report::msg(format!(
+                "IOHIDSetModifierLockState: {result:#x}"
+            ))),
         }
-        modifiers.set(mods);
-        true
-    } else {
-        false
     }
 }
 
+impl Drop for HidSystem {
+    fn drop(&mut self) {
+        unsafe { IOServiceClose(self.0) };
+    }
+}
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOServiceMatching(name: *const c_char) -> *mut c_void;
+    fn IOServiceGetMatchingService(main_port: u32, matching: *mut c_void) -> u32;
+    fn IOServiceOpen(service: u32, owning_task: u32, kind: u32, connect: *mut u32) -> i32;
+    fn IOServiceClose(connect: u32) -> i32;
+    fn IOObjectRelease(object: u32) -> i32;
+    fn IOHIDGetModifierLockState(connect: u32, selector: i32, state: *mut bool) -> i32;
+    fn IOHIDSetModifierLockState(connect: u32, selector: i32, state: bool) -> i32;
+}
+
+unsafe extern "C" {
+    // what mach_task_self() expands to
+    static mach_task_self_: u32;
+}
+
+fn update_modifiers(modifiers: &Cell<Modifiers>, key: u32, state: u8) -> bool {
+    let mut mods = modifiers.get();
+    let changed = mods.update_key(key, state);
+    modifiers.set(mods);
+    changed
+}
+
 fn set_modifiers(
-    active_modifiers: &Cell<XMods>,
+    active_modifiers: &Cell<Modifiers>,
     depressed: u32,
     latched: u32,
     locked: u32,
     group: u32,
 ) {
-    let depressed = XMods::from_bits(depressed).unwrap_or_default();
-    let _latched = XMods::from_bits(latched).unwrap_or_default();
-    let _locked = XMods::from_bits(locked).unwrap_or_default();
-    let _group = XMods::from_bits(group).unwrap_or_default();
-
-    // we only care about the depressed modifiers for now
-    active_modifiers.replace(depressed);
-}
-
-fn to_cgevent_flags(depressed: XMods) -> CGEventFlags {
-    let mut flags = CGEventFlags::empty();
-    if depressed.contains(XMods::ShiftMask) {
-        flags |= CGEventFlags::CGEventFlagShift;
-    }
-    if depressed.contains(XMods::LockMask) {
-        flags |= CGEventFlags::CGEventFlagAlphaShift;
-    }
-    if depressed.contains(XMods::ControlMask) {
-        flags |= CGEventFlags::CGEventFlagControl;
-    }
-    if depressed.contains(XMods::Mod1Mask) {
-        flags |= CGEventFlags::CGEventFlagAlternate;
-    }
-    if depressed.contains(XMods::Mod4Mask) {
-        flags |= CGEventFlags::CGEventFlagCommand;
-    }
-    flags
-}
-
-// From X11/X.h
-bitflags! {
-    #[repr(C)]
-    #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-    struct XMods: u32 {
-        const ShiftMask = (1<<0);
-        const LockMask = (1<<1);
-        const ControlMask = (1<<2);
-        const Mod1Mask = (1<<3);
-        const Mod2Mask = (1<<4);
-        const Mod3Mask = (1<<5);
-        const Mod4Mask = (1<<6);
-        const Mod5Mask = (1<<7);
-    }
+    let mut mods = active_modifiers.get();
+    mods.update_xkb(depressed, latched, locked, group);
+    active_modifiers.set(mods);
 }
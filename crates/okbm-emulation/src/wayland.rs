@@ -1,16 +1,17 @@
 use crate::*;
 
-use bitflags::bitflags;
-use std::collections::HashMap;
-use std::io;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
 use std::os::fd::{AsFd, OwnedFd};
+use std::os::unix::fs::FileExt;
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 use wayland_client::WEnum;
 use wayland_client::backend::WaylandError;
 
 use wayland_client::protocol::wl_keyboard::{self, WlKeyboard};
-use wayland_client::protocol::wl_pointer::{Axis, ButtonState};
+use wayland_client::protocol::wl_pointer::{self, Axis, ButtonState};
 use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_protocols_wlr::virtual_pointer::v1::client::{
     zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1 as VpManager,
@@ -29,7 +30,9 @@ use wayland_client::{
 };
 
 struct State {
-    keymap: Option<(u32, OwnedFd, u32)>,
+    // keymap of the seat in the XKB text format
+    keymap: Option<String>,
+    lock_sync: LockSync,
     input_for_client: HashMap<u32, VirtualInput>,
     seat: wl_seat::WlSeat,
     qh: QueueHandle<Self>,
@@ -40,6 +43,14 @@ struct State {
 // App State, implements Dispatch event handlers
 pub(crate) struct WlrootsEmulation {
     last_flush_failed: bool,
+    // the connection is lost, the error has been reported and events are dropped
+    closed: bool,
+    coalesce_motion: bool,
+    // pointer events of the frame currently being received, per handle
+    frames: HashMap<u32, Vec<Event>>,
+    // motion accumulated per handle while the compositor can't keep up
+    pending_motion: HashMap<u32, (u32, f64, f64)>,
+    dropped: HashMap<u32, u64>,
     state: State,
     queue: EventQueue<State>,
 }
@@ -59,8 +70,14 @@ impl WlrootsEmulation {
 
         let mut emulate = WlrootsEmulation {
             last_flush_failed: false,
+            closed: false,
+            coalesce_motion: true,
+            frames: HashMap::new(),
+            pending_motion: HashMap::new(),
+            dropped: HashMap::new(),
             state: State {
                 keymap: None,
+                lock_sync: LockSync::default(),
                 input_for_client,
                 seat,
                 vpm,
@@ -69,12 +86,10 @@ impl WlrootsEmulation {
             },
             queue,
         };
-        while emulate.state.keymap.is_none() {
-            emulate.queue.blocking_dispatch(&mut emulate.state)?;
-        }
-        // let fd = unsafe { &File::from_raw_fd(emulate.state.keymap.unwrap().1.as_raw_fd()) };
-        // let mmap = unsafe { MmapOptions::new().map_copy(fd).unwrap() };
-        // log::debug!("{:?}", &mmap[..100]);
+        // seat capabilities, then the keymap of its keyboard if there is one,
+        // peers without a keymap of their own use it
+        emulate.queue.roundtrip(&mut emulate.state)?;
+        emulate.queue.roundtrip(&mut emulate.state)?;
         Ok(emulate)
     }
 }
@@ -84,116 +99,456 @@ impl State {
         let pointer: Vp = self.vpm.create_virtual_pointer(None, &self.qh, ());
         let keyboard: Vk = self.vkm.create_virtual_keyboard(&self.seat, &self.qh, ());
 
-        // TODO: use server side keymap
-        if let Some((format, fd, size)) = self.keymap.as_ref() {
-            keyboard.keymap(*format, fd.as_fd(), *size);
-        } else {
-            panic!("no keymap");
-        }
-
-        let vinput = VirtualInput {
+        let mut vinput = VirtualInput {
             pointer,
             keyboard,
-            modifiers: Arc::new(Mutex::new(XMods::empty())),
+            modifiers: Arc::new(Mutex::new(Modifiers::default())),
+            keymap: None,
+            xkb: None,
+            bindings: vec![],
+            pressed_keysyms: HashMap::new(),
+            pressed_buttons: HashSet::new(),
+            lock_sync: self.lock_sync,
         };
+        if let Some(keymap) = self.keymap.as_ref()
+            && let Err(e) = vinput.install_keymap(keymap, KeymapSource::Seat)
+        {
+            eprintln!("failed to install keymap: {e}");
+        }
 
         self.input_for_client.insert(client, vinput);
     }
+
+    // install the keymap of the seat on the keyboards whose peer didn't send one
+    fn update_seat_keymap(&mut self) {
+        let Some(keymap) = self.keymap.as_ref() else {
+            return;
+        };
+        for vinput in self.input_for_client.values_mut() {
+            if vinput.keymap != Some(KeymapSource::Peer)
+                && let Err(e) = vinput.install_keymap(keymap, KeymapSource::Seat)
+            {
+                eprintln!("failed to install keymap: {e}");
+            }
+        }
+    }
 }
 
 impl WlrootsEmulation {
     pub async fn consume(&mut self, event: Event, handle: u32) -> Result<()> {
-        if let Some(virtual_input) = self.state.input_for_client.get(&handle) {
-            if self.last_flush_failed {
-                match self.queue.flush() {
-                    Err(WaylandError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
-                        /*
-                         * outgoing buffer is full - sending more events
-                         * will overwhelm the output buffer and leave the
-                         * wayland connection in a broken state
-                         */
-                        println!("can't keep up, discarding event: ({handle}) - {event:?}");
+        if self.closed || !self.state.input_for_client.contains_key(&handle) {
+            return Ok(());
+        }
+
+        // pointer events are held back until the end of their frame
+        // and then sent to the compositor together
+        let events = match event {
+            Event::Pointer(PointerEvent::Frame) => {
+                let mut events = self.frames.remove(&handle).unwrap_or_default();
+                events.push(event);
+                events
+            }
+            Event::Pointer(_) => {
+                self.frames.entry(handle).or_default().push(event);
+                return Ok(());
+            }
+            Event::Keyboard(_) => vec![event],
+        };
+
+        if self.last_flush_failed {
+            match self.queue.flush() {
+                Err(WaylandError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                    /*
+                     * outgoing buffer is full - sending more events
+                     * will overwhelm the output buffer and leave the
+                     * wayland connection in a broken state
+                     */
+                    if self.coalesce_motion && is_motion_frame(&events) {
+                        let motion = self.pending_motion.entry(handle).or_insert((0, 0., 0.));
+                        for event in &events {
+                            if let Event::Pointer(PointerEvent::Motion { time, dx, dy }) = event {
+                                *motion = (*time, motion.1 + dx, motion.2 + dy);
+                            }
+                        }
                         return Ok(());
                     }
-                    _ => {}
+
+                    // pending motion can't be merged with what comes after these events
+                    let discarded =
+                        events.len() as u64 + self.pending_motion.remove(&handle).is_some() as u64;
+                    println!("can't keep up, discarding events: ({handle}) - {events:?}");
+                    *self.dropped.entry(handle).or_default() += discarded;
+                    return Ok(());
                 }
+                _ => {}
+            }
+        }
+
+        let Some(virtual_input) = self.state.input_for_client.get_mut(&handle) else {
+            return Ok(());
+        };
+        if let Some((time, dx, dy)) = self.pending_motion.remove(&handle) {
+            let motion = Event::Pointer(PointerEvent::Motion { time, dx, dy });
+            for event in [motion, Event::Pointer(PointerEvent::Frame)] {
+                virtual_input
+                    .consume_event(event)
+                    .map_err(|_| EmulationError::InvalidEvent(event))?;
             }
+        }
+        for &event in &events {
             virtual_input
                 .consume_event(event)
-                .unwrap_or_else(|_| panic!("failed to convert event: {event:?}"));
-            match self.queue.flush() {
-                Err(WaylandError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
-                    self.last_flush_failed = true;
-                    println!("can't keep up, discarding event: ({handle}) - {event:?}");
+                .map_err(|_| EmulationError::InvalidEvent(event))?;
+        }
+        if !self.flush()? {
+            println!("can't keep up, discarding events: ({handle}) - {events:?}");
+        }
+        Ok(())
+    }
+
+    pub fn set_motion_coalescing(&mut self, enabled: bool) {
+        self.coalesce_motion = enabled;
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending_motion.is_empty()
+    }
+
+    // sends the motion held back while the compositor couldn't keep up,
+    // once it has taken what was queued before
+    pub fn flush_pending(&mut self) -> Result<()> {
+        if self.pending_motion.is_empty() || !self.flush()? {
+            return Ok(());
+        }
+        for (handle, (time, dx, dy)) in self.pending_motion.drain() {
+            if let Some(vinput) = self.state.input_for_client.get_mut(&handle) {
+                vinput.pointer.motion(time, dx, dy);
+                vinput.pointer.frame();
+            }
+        }
+        self.flush()?;
+        Ok(())
+    }
+
+    // whether everything could be sent, the compositor may not keep up.
+    // Losing the connection is reported once, nothing is sent afterwards
+    fn flush(&mut self) -> Result<bool> {
+        if self.closed {
+            return Ok(false);
+        }
+        match self.queue.flush() {
+            Err(WaylandError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.last_flush_failed = true;
+                Ok(false)
+            }
+            Err(e) => {
+                self.closed = true;
+                self.pending_motion.clear();
+                match e {
+                    WaylandError::Protocol(e) => Err(EmulationError::Protocol(e.to_string()))?,
+                    e => Err(e)?,
                 }
-                Err(WaylandError::Protocol(e)) => panic!("wayland protocol violation: {e}"),
-                Ok(()) => self.last_flush_failed = false,
-                Err(e) => Err(e)?,
             }
+            Ok(()) => {
+                self.last_flush_failed = false;
+                Ok(true)
+            }
+        }
+    }
+
+    pub fn dropped(&self, handle: u32) -> u64 {
+        self.dropped.get(&handle).copied().unwrap_or_default()
+    }
+
+    pub fn set_repeat(&mut self, handle: u32, repeat: RepeatInfo) {
+        // clients repeat keys themselves at the rate the compositor advertises,
+        // virtual keyboards have no way to change it
+        println!("key repeat of {handle} is up to the compositor, ignoring {repeat:?}");
+    }
+
+    pub fn set_lock_sync(&mut self, lock_sync: LockSync) {
+        self.state.lock_sync = lock_sync;
+        for vinput in self.state.input_for_client.values_mut() {
+            vinput.lock_sync = lock_sync;
+        }
+    }
+
+    pub fn sync_locks(&mut self, handle: u32, locks: XMods) -> Result<()> {
+        let Some(vinput) = self.state.input_for_client.get_mut(&handle) else {
+            return Ok(());
+        };
+        vinput.sync_locks(locks);
+        self.flush()?;
+        Ok(())
+    }
+
+    pub fn set_keymap(&mut self, handle: u32, keymap: &Keymap) -> Result<()> {
+        let Some(vinput) = self.state.input_for_client.get_mut(&handle) else {
+            return Ok(());
+        };
+
+        vinput.install_keymap(&keymap.xkb, KeymapSource::Peer)?;
+
+        // a new keymap resets the modifiers, restore them along with the layout
+        if let Ok(mut mods) = vinput.modifiers.lock() {
+            mods.group = keymap.group;
+            let (depressed, latched, locked, group) = mods.xkb_masks();
+            vinput.keyboard.modifiers(depressed, latched, locked, group);
         }
+
+        self.flush()?;
         Ok(())
     }
 
     pub async fn create(&mut self, handle: u32) {
         self.state.add_client(handle);
-        if let Err(e) = self.queue.flush() {
+        if let Err(e) = self.flush() {
             eprintln!("{}", e);
         }
     }
+
+    pub fn destroy(&mut self, handle: u32) -> Result<()> {
+        self.frames.remove(&handle);
+        self.pending_motion.remove(&handle);
+        self.dropped.remove(&handle);
+        let Some(vinput) = self.state.input_for_client.remove(&handle) else {
+            return Ok(());
+        };
+        vinput.destroy();
+        self.flush()?;
+        Ok(())
+    }
+}
+
+// a frame made of relative motion only, which can be merged with other such frames
+fn is_motion_frame(events: &[Event]) -> bool {
+    events.iter().all(|e| {
+        matches!(
+            e,
+            Event::Pointer(PointerEvent::Motion { .. } | PointerEvent::Frame)
+        )
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeymapSource {
+    Seat,
+    Peer,
 }
 
 struct VirtualInput {
     pointer: Vp,
     keyboard: Vk,
-    modifiers: Arc<Mutex<XMods>>,
+    modifiers: Arc<Mutex<Modifiers>>,
+    // keys can't be sent before a keymap has been installed
+    keymap: Option<KeymapSource>,
+    // the installed keymap, without `bindings`
+    xkb: Option<xkb::Keymap>,
+    // (keycode, keysym) bound on top of the installed keymap for keysyms it doesn't have
+    bindings: Vec<(u32, u32)>,
+    // keycodes the pressed keysyms were sent with
+    pressed_keysyms: HashMap<u32, u32>,
+    // buttons pressed through the virtual pointer
+    pressed_buttons: HashSet<u32>,
+    lock_sync: LockSync,
+}
+
+fn upload_keymap(keyboard: &Vk, keymap: &str) -> io::Result<()> {
+    let mut file = tempfile::tempfile()?;
+    file.write_all(keymap.as_bytes())?;
+    // the keymap must be null terminated
+    file.write_all(&[0])?;
+    file.flush()?;
+
+    keyboard.keymap(
+        u32::from(wl_keyboard::KeymapFormat::XkbV1),
+        file.as_fd(),
+        keymap.len() as u32 + 1,
+    );
+    Ok(())
+}
+
+fn read_keymap(fd: OwnedFd, size: u32) -> io::Result<String> {
+    // the file offset may be shared with other clients, don't move it
+    let mut buf = vec![0; size as usize];
+    File::from(fd).read_exact_at(&mut buf, 0)?;
+
+    // the keymap is null terminated
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
 impl VirtualInput {
-    fn consume_event(&self, event: Event) -> Result<(), ()> {
+    fn install_keymap(&mut self, keymap: &str, source: KeymapSource) -> io::Result<()> {
+        upload_keymap(&self.keyboard, keymap)?;
+        self.keymap = Some(source);
+        self.xkb = xkb::Keymap::new(keymap);
+        self.bindings.clear();
+        Ok(())
+    }
+
+    // a keycode and the modifiers producing `keysym`, binding it to a spare keycode
+    // if the keymap doesn't have it
+    fn find_keysym(&mut self, keysym: u32) -> Option<(u32, u32)> {
+        let c = keysym::to_char(keysym)?;
+        let xkb = self.xkb.as_ref()?;
+        if let Some(found) = xkb.find(c, 0) {
+            return Some(found);
+        }
+        if let Some(&(keycode, _)) = self.bindings.iter().find(|(_, k)| *k == keysym) {
+            return Some((keycode, 0));
+        }
+
+        let spare: Vec<u32> = xkb
+            .unbound_keycodes()
+            .into_iter()
+            .filter(|&k| k >= 8)
+            .collect();
+        // start over once every spare keycode is in use
+        if self.bindings.len() >= spare.len() {
+            self.bindings.clear();
+        }
+        let keycode = *spare.get(self.bindings.len())?;
+        self.bindings.push((keycode, keysym));
+
+        let keymap = xkb.bind_keys(&self.bindings)?;
+        println!("binding {keysym:#x} to keycode {keycode}");
+        upload_keymap(&self.keyboard, &keymap).ok()?;
+        Some((keycode, 0))
+    }
+
+    fn keysym_event(&mut self, time: u32, keysym: u32, state: u8) {
+        if state == 0 {
+            if let Some(keycode) = self.pressed_keysyms.remove(&keysym) {
+                self.keyboard.key(time, keycode - 8, 0);
+            }
+            return;
+        }
+
+        let Some((keycode, level_mods)) = self.find_keysym(keysym) else {
+            println!("no key produces keysym {keysym:#x}");
+            return;
+        };
+        self.pressed_keysyms.insert(keysym, keycode);
+
+        // press the key with the modifiers selecting its level, shortcut modifiers are kept,
+        // caps lock would change the level
+        let mods = self.modifiers.lock().map(|m| *m).unwrap_or_default();
+        let level = XMods::ShiftMask | XMods::Mod5Mask;
+        let depressed = (mods.depressed() - level) | XMods::from_bits_truncate(level_mods);
+        let locked = mods.locked - XMods::LockMask;
+        self.keyboard
+            .modifiers(depressed.bits(), 0, locked.bits(), 0);
+        self.keyboard.key(time, keycode - 8, 1);
+        let (depressed, latched, locked, group) = mods.xkb_masks();
+        self.keyboard.modifiers(depressed, latched, locked, group);
+    }
+
+    // releases the keys pressed by keysym and the buttons, the compositor
+    // may keep them pressed once the virtual devices are destroyed
+    fn destroy(self) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u32;
+        for keycode in self.pressed_keysyms.into_values() {
+            self.keyboard.key(time, keycode - 8, 0);
+        }
+        if !self.pressed_buttons.is_empty() {
+            for button in self.pressed_buttons {
+                self.pointer.button(time, button, ButtonState::Released);
+            }
+            self.pointer.frame();
+        }
+        self.pointer.destroy();
+        self.keyboard.destroy();
+    }
+
+    // taps the lock keys whose state differs from `locked`
+    fn sync_locks(&mut self, locked: XMods) {
+        if self.keymap.is_none() {
+            return;
+        }
+        let Ok(mut mods) = self.modifiers.lock() else {
+            return;
+        };
+        let keys = mods.lock_keys_to(locked);
+        if keys.is_empty() {
+            return;
+        }
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u32;
+        for key in keys {
+            println!("toggling {key:?} to match the sender");
+            for state in [1, 0] {
+                self.keyboard.key(time, key as u32, state as u32);
+                mods.update_key(key as u32, state);
+            }
+        }
+        let (depressed, latched, locked, group) = mods.xkb_masks();
+        self.keyboard.modifiers(depressed, latched, locked, group);
+    }
+
+    fn consume_event(&mut self, event: Event) -> Result<(), ()> {
         let now: u32 = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u32;
 
         match event {
-            Event::Pointer(e) => {
-                match e {
-                    PointerEvent::Motion { time, dx, dy } => self.pointer.motion(time, dx, dy),
-                    PointerEvent::Button {
-                        time,
-                        button,
-                        state,
-                    } => {
-                        let state: ButtonState = state.try_into()?;
-                        self.pointer.button(time, button, state);
-                    }
-                    PointerEvent::Axis { time, axis, value } => {
-                        let axis: Axis = (axis as u32).try_into()?;
-                        self.pointer.axis(time, axis, value);
-                        self.pointer.frame();
-                    }
-                    PointerEvent::AxisDiscrete120 { axis, value } => {
-                        let axis: Axis = (axis as u32).try_into()?;
-                        self.pointer
-                            .axis_discrete(now, axis, value as f64 / 6., value / 120);
-                        self.pointer.frame();
-                    }
+            Event::Pointer(e) => match e {
+                PointerEvent::Motion { time, dx, dy } => self.pointer.motion(time, dx, dy),
+                PointerEvent::Button {
+                    time,
+                    button,
+                    state,
+                } => {
+                    let state: ButtonState = state.try_into()?;
+                    match state {
+                        ButtonState::Pressed => self.pressed_buttons.insert(button),
+                        _ => self.pressed_buttons.remove(&button),
+                    };
+                    self.pointer.button(time, button, state);
                 }
-                self.pointer.frame();
+                PointerEvent::Axis { time, axis, value } => {
+                    let axis: Axis = (axis as u32).try_into()?;
+                    self.pointer.axis(time, axis, value);
+                }
+                PointerEvent::AxisDiscrete120 { axis, value } => {
+                    let axis: Axis = (axis as u32).try_into()?;
+                    self.pointer
+                        .axis_discrete(now, axis, value as f64 / 6., value / 120);
+                }
+                PointerEvent::AxisSource { source } => {
+                    self.pointer.axis_source(match source {
+                        AxisSource::Wheel => wl_pointer::AxisSource::Wheel,
+                        AxisSource::Finger => wl_pointer::AxisSource::Finger,
+                        AxisSource::Continuous => wl_pointer::AxisSource::Continuous,
+                        AxisSource::WheelTilt => wl_pointer::AxisSource::WheelTilt,
+                    });
+                }
+                PointerEvent::AxisStop { time, axis } => {
+                    let axis: Axis = (axis as u32).try_into()?;
+                    self.pointer.axis_stop(time, axis);
+                }
+                PointerEvent::Frame => self.pointer.frame(),
+            },
+            Event::Keyboard(_) if self.keymap.is_none() => {
+                println!("no keymap yet, discarding {event:?}");
             }
             Event::Keyboard(e) => match e {
                 KeyboardEvent::Key { time, key, state } => {
                     self.keyboard.key(time, key, state as u32);
-                    if let Ok(mut mods) = self.modifiers.lock() {
-                        if mods.update_by_key_event(key, state) {
-                            println!("Key triggers modifier change: {:?}", mods);
-                            self.keyboard.modifiers(
-                                mods.mask_pressed().bits(),
-                                0,
-                                mods.mask_locks().bits(),
-                                0,
-                            );
-                        }
+                    if let Ok(mut mods) = self.modifiers.lock()
+                        && mods.update_key(key, state)
+                    {
+                        println!("Key triggers modifier change: {:?}", mods);
+                        let (depressed, latched, locked, group) = mods.xkb_masks();
+                        self.keyboard.modifiers(depressed, latched, locked, group);
                     }
                 }
                 KeyboardEvent::Modifiers {
@@ -203,12 +558,27 @@ impl VirtualInput {
                     group,
                 } => {
                     // Synchronize internal modifier state, assuming server is authoritative
+                    // unless our own locks win. The sender's locks may only be known once
+                    // it has keyboard focus, after the handshake, so they are synced here too.
+                    let mut mods_locked = mods_locked;
+                    if self.lock_sync == LockSync::Sender {
+                        self.sync_locks(XMods::from_bits_truncate(mods_locked) & XMods::LOCKS);
+                    }
                     if let Ok(mut mods) = self.modifiers.lock() {
-                        mods.update_by_mods_event(e);
+                        if self.lock_sync == LockSync::Receiver {
+                            mods_locked = mods.locked.bits();
+                        }
+                        mods.update_xkb(mods_depressed, mods_latched, mods_locked, group);
                     }
                     self.keyboard
                         .modifiers(mods_depressed, mods_latched, mods_locked, group);
                 }
+                KeyboardEvent::Keysym {
+                    time,
+                    key: _,
+                    keysym,
+                    state,
+                } => self.keysym_event(time, keysym, state),
             },
         }
         Ok(())
@@ -241,8 +611,19 @@ impl Dispatch<WlKeyboard, ()> for State {
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
-        if let wl_keyboard::Event::Keymap { format, fd, size } = event {
-            state.keymap = Some((u32::from(format), fd, size));
+        if let wl_keyboard::Event::Keymap {
+            format: WEnum::Value(wl_keyboard::KeymapFormat::XkbV1),
+            fd,
+            size,
+        } = event
+        {
+            match read_keymap(fd, size) {
+                Ok(keymap) => {
+                    state.keymap = Some(keymap);
+                    state.update_seat_keymap();
+                }
+                Err(e) => eprintln!("failed to read keymap: {e}"),
+            }
         }
     }
 }
@@ -259,81 +640,9 @@ impl Dispatch<WlSeat, ()> for State {
         if let wl_seat::Event::Capabilities {
             capabilities: WEnum::Value(capabilities),
         } = event
+            && capabilities.contains(wl_seat::Capability::Keyboard)
         {
-            if capabilities.contains(wl_seat::Capability::Keyboard) {
-                seat.get_keyboard(qhandle, ());
-            }
+            seat.get_keyboard(qhandle, ());
         }
     }
 }
-
-// From X11/X.h
-bitflags! {
-    #[repr(C)]
-    #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-    struct XMods: u32 {
-        const ShiftMask = (1<<0);
-        const LockMask = (1<<1);
-        const ControlMask = (1<<2);
-        const Mod1Mask = (1<<3);
-        const Mod2Mask = (1<<4);
-        const Mod3Mask = (1<<5);
-        const Mod4Mask = (1<<6);
-        const Mod5Mask = (1<<7);
-    }
-}
-
-impl XMods {
-    fn update_by_mods_event(&mut self, evt: KeyboardEvent) {
-        if let KeyboardEvent::Modifiers {
-            depressed, locked, ..
-        } = evt
-        {
-            *self = XMods::from_bits_truncate(depressed) | XMods::from_bits_truncate(locked);
-        }
-    }
-
-    fn update_by_key_event(&mut self, key: u32, state: u8) -> bool {
-        if let Ok(key) = scancode::Linux::try_from(key) {
-            println!("Attempting to process modifier from: {:#?}", key);
-            let pressed_mask = match key {
-                scancode::Linux::KeyLeftShift | scancode::Linux::KeyRightShift => XMods::ShiftMask,
-                scancode::Linux::KeyLeftCtrl | scancode::Linux::KeyRightCtrl => XMods::ControlMask,
-                scancode::Linux::KeyLeftAlt | scancode::Linux::KeyRightalt => XMods::Mod1Mask,
-                scancode::Linux::KeyLeftMeta | scancode::Linux::KeyRightmeta => XMods::Mod4Mask,
-                _ => XMods::empty(),
-            };
-
-            let locked_mask = match key {
-                scancode::Linux::KeyCapsLock => XMods::LockMask,
-                scancode::Linux::KeyNumlock => XMods::Mod2Mask,
-                scancode::Linux::KeyScrollLock => XMods::Mod3Mask,
-                _ => XMods::empty(),
-            };
-
-            // unchanged
-            if pressed_mask.is_empty() && locked_mask.is_empty() {
-                println!("{:#?} is not a modifier key", key);
-                return false;
-            }
-            match state {
-                1 => self.insert(pressed_mask),
-                _ => {
-                    self.remove(pressed_mask);
-                    self.toggle(locked_mask);
-                }
-            }
-            true
-        } else {
-            false
-        }
-    }
-
-    fn mask_locks(&self) -> XMods {
-        *self & (XMods::LockMask | XMods::Mod2Mask | XMods::Mod3Mask)
-    }
-
-    fn mask_pressed(&self) -> XMods {
-        *self & (XMods::ShiftMask | XMods::ControlMask | XMods::Mod1Mask | XMods::Mod4Mask)
-    }
-}
@@ -1,82 +1,869 @@
-use okbm::*;
-use serde::{Deserialize, Serialize};
+use okbm::{
+    coalesce::MotionCoalescer,
+    config::{Config, Discovery, Motion, PeerConfig, Repeat},
+    discovery::{Announcer, Beacon},
+    protocol::{ControlMessage, EventMessage, Handshake, ZenohEvent},
+    remap::Remapper,
+    stats::{ClockSync, PeerStats},
+    transfer, *,
+};
+use std::{
+    collections::HashMap,
+    env, fs, mem,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{sync::mpsc, time::MissedTickBehavior};
 
-#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
-pub struct ZenohEvent {
+// interval between two clock synchronization pings
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+// key events held while waiting for a peer's keymap, past that they are
+// emulated with whatever keymap is installed
+const MAX_HELD_KEYS: usize = 256;
+
+// interval between two attempts to send the motion the emulation backend held back
+const EMULATION_RETRY_INTERVAL: Duration = Duration::from_millis(4);
+
+// interval between two checks of the config file for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// seconds `okbm discover` listens for beacons by default
+const DISCOVER_TIME: u64 = 3;
+
+struct Peer {
+    publisher: zenoh::pubsub::Publisher<'static>,
+    control: zenoh::pubsub::Publisher<'static>,
+    // capture handle of the barrier leading to this peer
     handle: u32,
-    event: CaptureEvent,
+    // emulation handle of the events it sends us
+    emulation: u32,
+    edge: Edge,
+    // a disabled peer neither gets input nor controls us, see `okbmctl disable`
+    enabled: bool,
+    seq: u64,
+    remapper: Remapper,
+    coalescer: MotionCoalescer,
+    clock: ClockSync,
+    stats: PeerStats,
+    repeat: Repeat,
+    clipboard: bool,
+    primary_selection: bool,
+    files: bool,
+    // generation of each selection last sent to the peer
+    clipboard_sent: HashMap<Selection, u64>,
+    // hash of the keymap last sent to the peer
+    keymap_sent: Option<u64>,
+    // hash of the peer's keymap installed on its emulation handle
+    keymap_installed: Option<u64>,
+    // key events of the peer held until the keymap we asked it for arrives
+    held_keys: Option<Vec<Event>>,
+}
+
+impl Peer {
+    async fn new(
+        session: &zenoh::Session,
+        id: &str,
+        peer: &PeerConfig,
+        handle: u32,
+        motion: &Motion,
+    ) -> Result<Self> {
+        Ok(Self {
+            publisher: session
+                .declare_publisher(protocol::event_key(&peer.id, id))
+                .await
+                .map_err(Report::msg)?,
+            control: session
+                .declare_publisher(protocol::control_key(&peer.id, id))
+                .await
+                .map_err(Report::msg)?,
+            handle,
+            emulation: handle,
+            edge: peer.edge(),
+            enabled: true,
+            seq: 0,
+            remapper: Remapper::new(&peer.remap),
+            coalescer: MotionCoalescer::new(Duration::from_millis(motion.coalesce_window_ms)),
+            clock: ClockSync::default(),
+            stats: PeerStats::default(),
+            repeat: peer.repeat.clone(),
+            clipboard: peer.clipboard,
+            primary_selection: peer.primary_selection,
+            files: peer.files,
+            clipboard_sent: HashMap::new(),
+            keymap_sent: None,
+            keymap_installed: None,
+            held_keys: None,
+        })
+    }
+
+    // the settings that can change while running
+    fn configure(&mut self, peer: &PeerConfig, motion: &Motion) {
+        self.remapper = Remapper::new(&peer.remap);
+        self.coalescer = MotionCoalescer::new(Duration::from_millis(motion.coalesce_window_ms));
+        self.repeat = peer.repeat.clone();
+        self.clipboard = peer.clipboard;
+        self.primary_selection = peer.primary_selection;
+        self.files = peer.files;
+    }
+
+    async fn publish(&self, message: &EventMessage) -> Result<()> {
+        let bytes: Vec<u8> = bincode::serialize(message)?;
+
+        println!("Sending message: {:?}", bytes);
+        self.publisher.put(&bytes[..]).await.map_err(Report::msg)
+    }
+
+    async fn send(&mut self, timestamp: u64, event: CaptureEvent) -> Result<()> {
+        let message = ZenohEvent {
+            handle: self.handle,
+            seq: self.seq,
+            timestamp,
+            event,
+        };
+        self.seq += 1;
+        self.publish(&EventMessage::Event(message)).await
+    }
+
+    // the state of our input the peer reproduces, sent ahead of the events it applies to.
+    // The keymap is left out if the peer has it already
+    async fn enter(
+        &mut self,
+        keymap: Option<Keymap>,
+        locks: Option<u32>,
+        repeat: Option<RepeatInfo>,
+    ) -> Result<()> {
+        let keymap_hash = keymap.as_ref().map(protocol::keymap_hash);
+        let keymap = keymap.filter(|_| keymap_hash != self.keymap_sent);
+        self.keymap_sent = keymap_hash;
+        self.publish(&EventMessage::Enter(Handshake {
+            keymap_hash,
+            keymap,
+            locks,
+            repeat,
+        }))
+        .await
+    }
+
+    // remaps and coalesces a captured event before sending it
+    async fn forward(&mut self, event: CaptureEvent) -> Result<()> {
+        let timestamp = protocol::now();
+        for event in self.remapper.apply(event) {
+            for (timestamp, event) in self.coalescer.push(timestamp, event) {
+                self.send(timestamp, event).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // our input goes elsewhere, the keys still held are released and
+    // the motion held back is sent
+    async fn leave(&mut self, releases: Vec<KeyboardEvent>) -> Result<()> {
+        for release in releases {
+            self.forward(CaptureEvent::Input(Event::Keyboard(release)))
+                .await?;
+        }
+        for (timestamp, event) in self.coalescer.flush() {
+            self.send(timestamp, event).await?;
+        }
+        Ok(())
+    }
+
+    // applies the handshake of the peer, asking for its keymap if we don't have it
+    async fn entered(&mut self, emulation: &mut Emulation, handshake: Handshake) -> Result<()> {
+        let awaiting = match (&handshake.keymap, handshake.keymap_hash) {
+            (Some(keymap), hash) => {
+                match emulation.set_keymap(self.emulation, keymap) {
+                    Ok(()) => self.keymap_installed = hash,
+                    Err(e) => eprintln!("failed to install keymap: {e}"),
+                }
+                false
+            }
+            (None, Some(hash)) => self.keymap_installed != Some(hash),
+            (None, None) => false,
+        };
+        if let Some(locks) = handshake.locks
+            && let Err(e) = emulation.sync_locks(self.emulation, locks)
+        {
+            eprintln!("failed to sync locks: {e}");
+        }
+        emulation.set_repeat(self.emulation, self.repeat.resolve(handshake.repeat));
+
+        if !awaiting {
+            self.release_held_keys(emulation).await;
+        } else if self.held_keys.is_none() {
+            // we lost the keymap the peer sent us, its keys wait for it
+            self.held_keys = Some(vec![]);
+            self.send_control(&ControlMessage::KeymapRequest).await?;
+        }
+        Ok(())
+    }
+
+    async fn release_held_keys(&mut self, emulation: &mut Emulation) {
+        for event in self.held_keys.take().unwrap_or_default() {
+            if let Err(e) = emulation.consume(event, self.emulation).await {
+                eprintln!("failed to emulate {event:?}: {e}");
+            }
+        }
+    }
+
+    async fn send_control(&self, message: &ControlMessage) -> Result<()> {
+        let bytes: Vec<u8> = bincode::serialize(message)?;
+        self.control.put(&bytes[..]).await.map_err(Report::msg)
+    }
+
+    fn shares(&self, selection: Selection) -> bool {
+        match selection {
+            Selection::Clipboard => self.clipboard,
+            Selection::Primary => self.primary_selection,
+        }
+    }
+
+    // the generation of our clipboard or primary selection to send, unless the peer has it
+    fn clipboard_to_send(&self, clipboard: &Clipboard, selection: Selection) -> Option<u64> {
+        let generation = clipboard.generation(selection);
+        (self.shares(selection) && self.clipboard_sent.get(&selection) != Some(&generation))
+            .then_some(generation)
+    }
+
+    async fn send_clipboard(
+        &mut self,
+        selection: Selection,
+        generation: u64,
+        content: ClipboardContent,
+    ) -> Result<()> {
+        if !content.is_empty() {
+            self.send_control(&ControlMessage::Clipboard(selection, content))
+                .await?;
+        }
+        self.clipboard_sent.insert(selection, generation);
+        Ok(())
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let id = "192.168.1.49".to_string();
-    let position = Position::Right;
-    let neighbour = "192.168.1.34".to_string();
+    match env::args().nth(1).as_deref() {
+        None => run().await,
+        Some("status") => {
+            print!("{}", ipc::request("status").await?);
+            Ok(())
+        }
+        Some("discover") => discover().await,
+        Some(command) => Err(Report::msg(format!("unknown command: {command}"))),
+    }
+}
+
+// lists the daemons announcing themselves, `okbm discover [seconds]`
+async fn discover() -> Result<()> {
+    let seconds = match env::args().nth(2) {
+        Some(seconds) => seconds.parse()?,
+        None => DISCOVER_TIME,
+    };
+    // beacons are listened to on the group of our own config, if there is one
+    let group = Config::load(&Config::path())
+        .map(|config| config.discovery)
+        .unwrap_or_else(|_| Discovery::default())
+        .group;
+
+    let found = discovery::discover(group, Duration::from_secs(seconds)).await?;
+    if found.is_empty() {
+        println!("no peer found");
+    }
+    for (from, beacon) in found {
+        println!("{beacon}\n  from: {from}");
+    }
+    Ok(())
+}
+
+// creates the barrier of the edge leading to a peer and the emulation of its events
+async fn create_handle(
+    capture: &mut Capture,
+    emulation: &mut Emulation,
+    handle: u32,
+    peer: &PeerConfig,
+) -> Result<()> {
+    create_barrier(capture, handle, peer).await?;
+    emulation.create(handle).await;
+    Ok(())
+}
+
+// the barrier alone, nothing is left of it when it fails
+async fn create_barrier(capture: &mut Capture, handle: u32, peer: &PeerConfig) -> Result<()> {
+    capture.create(handle, peer.edge()).await?;
+    if let Err(e) = capture.set_switch_block(handle, peer.block.clone()).await {
+        let _ = capture.destroy(handle).await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+// the keymap sent to peers, in keysym mode they resolve characters with their own
+fn keymap(capture: &Capture, mode: KeyMode) -> Option<Keymap> {
+    match mode {
+        KeyMode::Scancode => capture.keymap(),
+        KeyMode::Keysym => None,
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/*
+ * applies the reloaded config, the sessions of the peers that stay are kept
+ * and a peer moved to another edge gets a new handle.
+ * Returns what only a restart applies.
+ */
+async fn reload(
+    config: &mut Config,
+    session: &zenoh::Session,
+    capture: &mut Capture,
+    emulation: &mut Emulation,
+    peers: &mut HashMap<String, Peer>,
+    next_handle: &mut u32,
+) -> Result<String> {
+    let new = Config::load(&Config::path())?;
+
+    let mut notes = String::new();
+    if (&new.id, &new.listen, &new.connect, &new.discovery)
+        != (
+            &config.id,
+            &config.listen,
+            &config.connect,
+            &config.discovery,
+        )
+    {
+        notes.push_str("restart to apply id, listen, connect and discovery\n");
+    }
+
+    capture.set_key_mode(new.keyboard.mode).await;
+    capture.set_switch_policy(new.switching).await;
+    emulation.set_motion_coalescing(new.motion.coalesce_on_backpressure);
+    emulation.set_lock_sync(new.keyboard.locks);
+
+    let removed: Vec<_> = peers
+        .keys()
+        .filter(|&name| !new.peers.iter().any(|p| &p.id == name))
+        .cloned()
+        .collect();
+    // each peer is applied on its own, one failing doesn't keep the others
+    // or the rest of the config from applying
+    for name in removed {
+        if let Some(peer) = peers.remove(&name) {
+            println!("removed peer {name}");
+            if let Err(e) = capture.destroy(peer.handle).await {
+                notes.push_str(&format!("failed to remove the barrier of {name}: {e}\n"));
+            }
+            if let Err(e) = emulation.destroy(peer.emulation).await {
+                notes.push_str(&format!("failed to remove the emulation of {name}: {e}\n"));
+            }
+        }
+    }
+    for peer_config in &new.peers {
+        // a peer that fails to apply keeps its previous handles and edge
+        let applied: Result<()> = async {
+            match peers.get_mut(&peer_config.id) {
+                Some(peer) if peer.edge == peer_config.edge() => {
+                    capture
+                        .set_switch_block(peer.handle, peer_config.block.clone())
+                        .await?;
+                }
+                Some(peer) => {
+                    println!("moved peer {}", peer_config.id);
+                    // the new barrier goes up before the old one comes down, the emulation
+                    // of the peer's events has nothing to do with the edge and stays
+                    create_barrier(capture, *next_handle, peer_config).await?;
+                    let previous = mem::replace(&mut peer.handle, *next_handle);
+                    peer.edge = peer_config.edge();
+                    *next_handle += 1;
+                    if let Err(e) = capture.destroy(previous).await {
+                        eprintln!(
+                            "failed to remove the previous barrier of {}: {e}",
+                            peer_config.id
+                        );
+                    }
+                }
+                None => {
+                    println!("added peer {}", peer_config.id);
+                    let peer =
+                        Peer::new(session, &config.id, peer_config, *next_handle, &new.motion)
+                            .await?;
+                    create_handle(capture, emulation, *next_handle, peer_config).await?;
+                    peers.insert(peer_config.id.clone(), peer);
+                    *next_handle += 1;
+                }
+            }
+            if let Some(peer) = peers.get_mut(&peer_config.id) {
+                peer.configure(peer_config, &new.motion);
+            }
+            Ok(())
+        }
+        .await;
+        if let Err(e) = applied {
+            notes.push_str(&format!("failed to apply peer {}: {e}\n", peer_config.id));
+        }
+    }
+
+    *config = Config {
+        id: config.id.clone(),
+        listen: config.listen.clone(),
+        connect: config.connect.clone(),
+        discovery: config.discovery.clone(),
+        ..new
+    };
+    Ok(notes)
+}
+
+async fn run() -> Result<()> {
+    let config_path = Config::path();
+    let mut config = Config::load(&config_path)?;
+    let mut config_modified = modified(&config_path);
+    let mut config_poll = tokio::time::interval(CONFIG_POLL_INTERVAL);
+    let id = config.id.clone();
 
     zenoh::try_init_log_from_env();
 
-    let mut config = zenoh::Config::default();
-    config
-        .insert_json5("connect/endpoints", r#"["udp/192.168.1.34:4242"]"#)
+    let mut zenoh_config = zenoh::Config::default();
+    zenoh_config
+        .insert_json5("connect/endpoints", &format!("{:?}", config.connect))
         .map_err(Report::msg)?;
 
-    config
-        .insert_json5("listen/endpoints", r#"["udp/192.168.1.49:4242"]"#)
+    zenoh_config
+        .insert_json5("listen/endpoints", &format!("{:?}", config.listen))
         .map_err(Report::msg)?;
 
-    let session = zenoh::open(config).await.map_err(Report::msg)?;
+    let session = zenoh::open(zenoh_config).await.map_err(Report::msg)?;
 
     let subscriber = session
-        .declare_subscriber(format!("okbm/{}", id))
+        .declare_subscriber(protocol::event_key(&id, "*"))
         .await
         .map_err(Report::msg)?;
 
-    let publisher = session
-        .declare_publisher(format!("okbm/{}", neighbour))
+    let control_subscriber = session
+        .declare_subscriber(protocol::control_key(&id, "*"))
         .await
         .map_err(Report::msg)?;
 
+    let mut requests = ipc::listen()?;
+    // selections read for the peer of a capture handle, with their generation
+    let (clipboard_tx, mut clipboard_rx) = mpsc::unbounded_channel();
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+
+    let announcer = match config.discovery.announce {
+        true => Some(Announcer::new(config.discovery.group).await?),
+        false => None,
+    };
+    let mut announce =
+        tokio::time::interval(Duration::from_millis(config.discovery.interval_ms.max(1)));
+    let fingerprint = session.zid().to_string();
+
     let mut capture = Capture::new().await?;
-    capture.create(0, position).await?;
+    capture.set_key_mode(config.keyboard.mode).await;
+    capture.set_switch_policy(config.switching).await;
 
     let mut emulation = Emulation::new()?;
-    emulation.create(0).await;
+    let mut emulation_retry = tokio::time::interval(EMULATION_RETRY_INTERVAL);
+    emulation_retry.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    emulation.set_motion_coalescing(config.motion.coalesce_on_backpressure);
+    emulation.set_lock_sync(config.keyboard.locks);
+
+    let mut clipboard = Clipboard::new()
+        .inspect_err(|e| eprintln!("clipboard sharing unavailable: {e}"))
+        .ok();
+
+    // handle of the peer we send input to
+    let mut active = None;
+
+    let mut peers = HashMap::new();
+    // handles of removed peers aren't reused
+    let mut next_handle = 0;
+    for peer in &config.peers {
+        create_handle(&mut capture, &mut emulation, next_handle, peer).await?;
+        peers.insert(
+            peer.id.clone(),
+            Peer::new(&session, &id, peer, next_handle, &config.motion).await?,
+        );
+        next_handle += 1;
+    }
 
     loop {
+        let deadline = peers.values().filter_map(|p| p.coalescer.deadline()).min();
+
         tokio::select! {
-            Some(Ok(event)) = capture.next() => {
+            Some(event) = capture.next() => {
+                // the backend keeps going after most errors, it ends the stream otherwise
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        eprintln!("capture error: {e}");
+                        continue;
+                    }
+                };
                 if let CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key { key: 1, .. })) = event.1 {
                     capture.release().await?;
+                    active = None;
+
+                    continue;
+                }
 
+                // keys typed as keysyms complete chords by the key typing them
+                if let CaptureEvent::Input(Event::Keyboard(
+                    KeyboardEvent::Key { time, key, state: 1 } | KeyboardEvent::Keysym { time, key, state: 1, .. },
+                )) = event.1
+                    && let Ok(key) = scancode::Linux::try_from(key)
+                    && let Some(hotkey) = config.remote_hotkeys.iter().find(|h| h.matches(capture.pressed_keys(), key))
+                {
+                    // the peer never sees the last key of the chord, the rest is released
+                    let releases = capture.releases(time).filter(|&(k, _)| k != key).map(|(_, release)| release).collect();
+                    if let Some(peer) = peers.values_mut().find(|p| p.handle == event.0) {
+                        peer.leave(releases).await?;
+                    }
+
+                    match hotkey.peer.as_ref() {
+                        Some(id) => match peers.get(id) {
+                            Some(target) => {
+                                if let Err(e) = capture.grab(target.handle).await {
+                                    eprintln!("failed to switch to {id}: {e}");
+                                }
+                            }
+                            None => eprintln!("hotkey for unknown peer {id}"),
+                        },
+                        None => {
+                            capture.release().await?;
+                            active = None;
+                        }
+                    }
                     continue;
                 }
 
-                let event = ZenohEvent { handle: event.0, event: event.1 };
+                let Some(peer) = peers.values_mut().find(|p| p.handle == event.0) else {
+                    continue;
+                };
 
-                let bytes: Vec<u8> = bincode::serialize(&event)?;
+                // the edge of a disabled peer gives the pointer back right away
+                if !peer.enabled {
+                    if event.1 == CaptureEvent::Begin {
+                        capture.release().await?;
+                    }
+                    continue;
+                }
 
-                println!("Sending message: {:?}", bytes);
-                publisher.put(&bytes[..]).await.map_err(Report::msg)?;
+                if let CaptureEvent::Dropped(paths) = &event.1 {
+                    for path in paths.iter().filter(|_| peer.files) {
+                        match transfer::read(path, config.files.max_size).await {
+                            Ok((name, data)) => peer.send_control(&ControlMessage::File { name, data }).await?,
+                            Err(e) => eprintln!("failed to send {}: {e}", path.display()),
+                        }
+                    }
+                    continue;
+                }
+
+                if event.1 == CaptureEvent::Begin {
+                    active = Some(peer.handle);
+
+                    peer.enter(keymap(&capture, config.keyboard.mode), capture.locks(), capture.repeat_info()).await?;
+                    peer.remapper.reset();
+
+                    // read in a task, a slow source mustn't hold the input back
+                    if let Some(clipboard) = &clipboard {
+                        for selection in [Selection::Clipboard, Selection::Primary] {
+                            let Some(generation) = peer.clipboard_to_send(clipboard, selection) else {
+                                continue;
+                            };
+                            let (clipboard, clipboard_tx) = (clipboard.clone(), clipboard_tx.clone());
+                            let (handle, max_size) = (peer.handle, config.clipboard.max_size);
+                            tokio::spawn(async move {
+                                let content = clipboard.read(selection, max_size).await;
+                                let _ = clipboard_tx.send((handle, selection, generation, content));
+                            });
+                        }
+                    }
+                }
+
+                peer.forward(event.1).await?;
+            }
+
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now).into()), if deadline.is_some() => {
+                let now = Instant::now();
+                for peer in peers.values_mut() {
+                    if peer.coalescer.deadline().is_some_and(|d| d <= now) {
+                        for (timestamp, event) in peer.coalescer.flush() {
+                            peer.send(timestamp, event).await?;
+                        }
+                    }
+                }
+            }
+
+            Some((handle, selection, generation, content)) = clipboard_rx.recv() => {
+                let Some(peer) = peers.values_mut().find(|p| p.handle == handle) else {
+                    continue;
+                };
+                let result = match content {
+                    Ok(content) => peer.send_clipboard(selection, generation, content).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("failed to send the {selection:?} selection: {e}");
+                }
+            }
+
+            _ = emulation_retry.tick(), if emulation.has_pending() => {
+                if let Err(e) = emulation.flush_pending() {
+                    eprintln!("failed to send pending motion: {e}");
+                }
             }
 
             Ok(message) = subscriber.recv_async() => {
                 let bytes = message.payload().to_bytes();
                 println!("Received message: {:?}", bytes);
 
-                let message: ZenohEvent = bincode::deserialize(&bytes[..])?;
+                let Some(name) = protocol::sender(message.key_expr().as_str()) else {
+                    continue;
+                };
+                let Some(peer) = peers.get_mut(name) else {
+                    continue;
+                };
+
+                // a malformed message or one of a newer version doesn't stop us
+                let message: EventMessage = match bincode::deserialize(&bytes[..]) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        eprintln!("invalid event message from {name}: {e}");
+                        continue;
+                    }
+                };
+
+                if !peer.enabled {
+                    continue;
+                }
 
-                let handle = message.handle;
-                let event = message.event;
+                let message = match message {
+                    EventMessage::Event(message) => message,
+                    EventMessage::Enter(handshake) => {
+                        peer.entered(&mut emulation, handshake).await?;
+                        continue;
+                    }
+                };
 
-                match event {
+                match message.event {
                     CaptureEvent::Begin => {
                         capture.release().await?;
+                        active = None;
+                    }
+                    CaptureEvent::Input(event @ Event::Keyboard(_)) if peer.held_keys.is_some() => {
+                        let held = peer.held_keys.get_or_insert_default();
+                        held.push(event);
+                        if held.len() >= MAX_HELD_KEYS {
+                            eprintln!("no keymap from {name}, emulating its keys with the installed one");
+                            peer.release_held_keys(&mut emulation).await;
+                        }
                     }
                     CaptureEvent::Input(event) => {
-                        emulation.consume(event, handle).await?;
+                        if let Err(e) = emulation.consume(event, peer.emulation).await {
+                            eprintln!("failed to emulate {event:?}: {e}");
+                        }
+                    }
+                    // files go over the control channel
+                    CaptureEvent::Dropped(_) => {}
+                }
+
+                // capture to emulation, translated into our clock
+                let latency = peer.clock.offset().map(|offset| {
+                    (protocol::now() as i64 - (message.timestamp as i64 - offset)).max(0) as u64
+                });
+                peer.stats.record(message.seq, latency);
+            }
+
+            Ok(message) = control_subscriber.recv_async() => {
+                let Some(name) = protocol::sender(message.key_expr().as_str()) else {
+                    continue;
+                };
+                let Some(peer) = peers.get_mut(name) else {
+                    continue;
+                };
+
+                let message: ControlMessage = match bincode::deserialize(&message.payload().to_bytes()[..]) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        eprintln!("invalid control message from {name}: {e}");
+                        continue;
+                    }
+                };
+
+                // a disabled peer only keeps its clock in sync
+                if !peer.enabled && !matches!(message, ControlMessage::Ping { .. } | ControlMessage::Pong { .. }) {
+                    continue;
+                }
+
+                match message {
+                    ControlMessage::Ping { t0 } => {
+                        peer.send_control(&ControlMessage::Pong { t0, t1: protocol::now() }).await?;
+                    }
+                    ControlMessage::Pong { t0, t1 } => {
+                        peer.clock.update(t0, t1, protocol::now());
+                    }
+                    ControlMessage::KeymapRequest => {
+                        peer.keymap_sent = None;
+                        if active == Some(peer.handle) {
+                            peer.enter(keymap(&capture, config.keyboard.mode), capture.locks(), capture.repeat_info()).await?;
+                        }
+                    }
+                    ControlMessage::Clipboard(selection, content) => {
+                        if let Some(clipboard) = clipboard.as_mut()
+                            && peer.shares(selection)
+                            && content.size() <= config.clipboard.max_size
+                        {
+                            match clipboard.write(selection, content) {
+                                // the peer has it, it isn't sent back
+                                Ok(generation) => {
+                                    peer.clipboard_sent.insert(selection, generation);
+                                }
+                                Err(e) => eprintln!("failed to write the {selection:?} selection: {e}"),
+                            }
+                        }
+                    }
+                    ControlMessage::File { name: file, data } => {
+                        if !peer.files || data.len() as u64 > config.files.max_size {
+                            continue;
+                        }
+                        match transfer::save(&config.files.inbox(), &file, &data) {
+                            Ok(path) => transfer::notify(&format!("File received from {name}"), &path.display().to_string()),
+                            Err(e) => eprintln!("failed to save {file:?} from {name}: {e}"),
+                        }
                     }
                 }
             }
+
+            _ = ping.tick() => {
+                let ping = bincode::serialize(&ControlMessage::Ping { t0: protocol::now() })?;
+                for peer in peers.values() {
+                    peer.control.put(&ping[..]).await.map_err(Report::msg)?;
+                }
+            }
+
+            _ = config_poll.tick() => {
+                let modified = modified(&config_path);
+                if modified == config_modified {
+                    continue;
+                }
+                config_modified = modified;
+
+                match reload(&mut config, &session, &mut capture, &mut emulation, &mut peers, &mut next_handle).await {
+                    Ok(notes) => print!("reloaded {}\n{notes}", config_path.display()),
+                    Err(e) => eprintln!("failed to reload {}: {e}", config_path.display()),
+                }
+                // the peer we were sending input to was removed or moved
+                if active.is_some_and(|handle| !peers.values().any(|p| p.handle == handle)) {
+                    capture.release().await?;
+                    active = None;
+                }
+            }
+
+            _ = announce.tick(), if announcer.is_some() => {
+                let beacon = Beacon {
+                    id: id.clone(),
+                    fingerprint: fingerprint.clone(),
+                    listen: config.listen.clone(),
+                    screens: capture.screens(),
+                };
+                if let Some(announcer) = &announcer
+                    && let Err(e) = announcer.announce(&beacon).await
+                {
+                    eprintln!("failed to announce ourselves: {e}");
+                }
+            }
+
+            _ = tokio::signal::ctrl_c() => {
+                capture.terminate().await?;
+                return Ok(());
+            }
+
+            Some(request) = requests.recv() => {
+                let mut words = request.command.split_whitespace();
+                let response = match (words.next(), words.next(), words.next()) {
+                    (Some("status"), None, _) => {
+                        let mut status: String = peers
+                            .iter()
+                            .map(|(name, peer)| {
+                                let dropped = emulation.dropped(peer.emulation);
+                                format!("{}\n", peer.stats.report(name, &peer.clock, dropped))
+                            })
+                            .collect();
+                        if !emulation.applies_repeat() {
+                            status.push_str("key repeat: up to the compositor, the repeat settings of peers are ignored\n");
+                        }
+                        status
+                    }
+                    // one line per peer: id, position, output, segment, enabled or disabled, active
+                    (Some("peers"), None, _) => {
+                        let mut lines: Vec<_> = peers
+                            .iter()
+                            .map(|(name, peer)| {
+                                format!(
+                                    "{name}\t{}\t{}\t{}-{}\t{}\t{}\n",
+                                    format!("{:?}", peer.edge.position).to_lowercase(),
+                                    peer.edge.output.as_deref().unwrap_or("-"),
+                                    peer.edge.start,
+                                    peer.edge.end,
+                                    if peer.enabled { "enabled" } else { "disabled" },
+                                    if active == Some(peer.handle) { "active" } else { "-" },
+                                )
+                            })
+                            .collect();
+                        lines.sort();
+                        lines.concat()
+                    }
+                    (Some(command @ ("enable" | "disable")), Some(name), None) => match peers.get_mut(name) {
+                        Some(peer) => {
+                            peer.enabled = command == "enable";
+                            if !peer.enabled && active == Some(peer.handle) {
+                                capture.release().await?;
+                                active = None;
+                            }
+                            "ok\n".to_owned()
+                        }
+                        None => format!("error: unknown peer {name}\n"),
+                    },
+                    (Some("release"), None, _) => match capture.release().await {
+                        Ok(()) => {
+                            active = None;
+                            "ok\n".to_owned()
+                        }
+                        Err(e) => format!("error: {e}\n"),
+                    },
+                    (Some("switch"), Some(name), None) => match peers.get(name).map(|p| (p.handle, p.enabled)) {
+                        Some((handle, true)) => {
+                            // the peer that had our input is left as with a hotkey
+                            if let Some(previous) = active.filter(|&a| a != handle)
+                                && let Some(peer) = peers.values_mut().find(|p| p.handle == previous)
+                            {
+                                let time = (protocol::now() / 1000) as u32;
+                                peer.leave(capture.releases(time).map(|(_, release)| release).collect()).await?;
+                            }
+                            match capture.grab(handle).await {
+                                Ok(()) => "ok\n".to_owned(),
+                                Err(e) => format!("error: {e}\n"),
+                            }
+                        }
+                        Some(_) => format!("error: {name} is disabled\n"),
+                        None => format!("error: unknown peer {name}\n"),
+                    },
+                    (Some("reload"), None, _) => {
+                        let response = match reload(&mut config, &session, &mut capture, &mut emulation, &mut peers, &mut next_handle).await {
+                            Ok(notes) => format!("ok\n{notes}"),
+                            Err(e) => format!("error: {e}\n"),
+                        };
+                        if active.is_some_and(|handle| !peers.values().any(|p| p.handle == handle)) {
+                            capture.release().await?;
+                            active = None;
+                        }
+                        response
+                    }
+                    _ => format!("error: unknown command: {}\n", request.command),
+                };
+                let _ = request.reply.send(response);
+            }
         }
     }
 }
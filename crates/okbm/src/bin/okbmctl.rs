@@ -0,0 +1,24 @@
+use okbm::{Report, Result, ipc};
+use std::{env, process::ExitCode};
+
+const USAGE: &str = "usage: okbmctl status | peers | enable <peer> | disable <peer> | release | switch <peer> | reload";
+
+/*
+ * sends its arguments as a command to the running daemon and prints the answer,
+ * failing when the daemon answers with an error
+ */
+#[tokio::main]
+async fn main() -> Result<ExitCode> {
+    let command = env::args().skip(1).collect::<Vec<_>>().join(" ");
+    if command.is_empty() {
+        return Err(Report::msg(USAGE));
+    }
+
+    let response = ipc::request(&command).await?;
+    print!("{response}");
+
+    Ok(match response.starts_with("error:") {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    })
+}
@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+
+use crate::{CaptureEvent, Event, PointerEvent};
+
+#[derive(Debug)]
+struct PendingMotion {
+    // capture timestamp of the first merged event
+    timestamp: u64,
+    time: u32,
+    dx: f64,
+    dy: f64,
+    deadline: Instant,
+}
+
+/// Sums consecutive pointer motion events over a time window so a high rate
+/// mouse doesn't produce one message per report. Pointer events are held until
+/// their frame ends, frames containing nothing but motion are merged into a
+/// single one and any other frame is sent whole after the pending motion,
+/// motion is never merged across buttons or keys.
+#[derive(Debug)]
+pub struct MotionCoalescer {
+    window: Duration,
+    // pointer events of the frame being received, with their capture timestamps
+    frame: Vec<(u64, CaptureEvent)>,
+    pending: Option<PendingMotion>,
+}
+
+impl MotionCoalescer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            frame: vec![],
+            pending: None,
+        }
+    }
+
+    /// Feeds an event captured at `timestamp` and returns the events that are ready to be sent.
+    pub fn push(&mut self, timestamp: u64, event: CaptureEvent) -> Vec<(u64, CaptureEvent)> {
+        if self.window.is_zero() {
+            return vec![(timestamp, event)];
+        }
+
+        match event {
+            CaptureEvent::Input(Event::Pointer(PointerEvent::Frame)) => {
+                let frame = std::mem::take(&mut self.frame);
+                let motion_only = frame.iter().all(|(_, e)| {
+                    matches!(
+                        e,
+                        CaptureEvent::Input(Event::Pointer(PointerEvent::Motion { .. }))
+                    )
+                });
+                if !frame.is_empty() && motion_only {
+                    for (timestamp, event) in frame {
+                        if let CaptureEvent::Input(Event::Pointer(PointerEvent::Motion {
+                            time,
+                            dx,
+                            dy,
+                        })) = event
+                        {
+                            self.merge(timestamp, time, dx, dy);
+                        }
+                    }
+                    return vec![];
+                }
+
+                let mut events = self.flush();
+                events.extend(frame);
+                events.push((timestamp, event));
+                events
+            }
+            CaptureEvent::Input(Event::Pointer(_)) => {
+                self.frame.push((timestamp, event));
+                vec![]
+            }
+            event => {
+                let mut events = self.flush();
+                events.push((timestamp, event));
+                events
+            }
+        }
+    }
+
+    fn merge(&mut self, timestamp: u64, time: u32, dx: f64, dy: f64) {
+        match self.pending.as_mut() {
+            Some(pending) => {
+                pending.time = time;
+                pending.dx += dx;
+                pending.dy += dy;
+            }
+            None => {
+                self.pending = Some(PendingMotion {
+                    timestamp,
+                    time,
+                    dx,
+                    dy,
+                    deadline: Instant::now() + self.window,
+                });
+            }
+        }
+    }
+
+    /// When the pending motion has to be sent.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|p| p.deadline)
+    }
+
+    /// The pending motion as a frame of its own, the frame being received is kept.
+    pub fn flush(&mut self) -> Vec<(u64, CaptureEvent)> {
+        let Some(p) = self.pending.take() else {
+            return vec![];
+        };
+
+        let motion = PointerEvent::Motion {
+            time: p.time,
+            dx: p.dx,
+            dy: p.dy,
+        };
+        vec![
+            (p.timestamp, CaptureEvent::Input(Event::Pointer(motion))),
+            (
+                p.timestamp,
+                CaptureEvent::Input(Event::Pointer(PointerEvent::Frame)),
+            ),
+        ]
+    }
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    env,
+    net::{Ipv4Addr, SocketAddrV4},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Edge, KeyMode, LockSync, Position, RepeatInfo, Report, Result, SwitchBlock, SwitchPolicy,
+    remap::Rule, scancode::Linux,
+};
+
+/// Daemon configuration, read from `$XDG_CONFIG_HOME/okbm/config.toml`
+/// or the file pointed to by `OKBM_CONFIG`.
+///
+/// ```toml
+/// id = "192.168.1.49"
+/// listen = ["udp/192.168.1.49:4242"]
+/// connect = ["udp/192.168.1.34:4242"]
+///
+/// [motion]
+/// coalesce_window_ms = 4
+///
+/// [switching]
+/// corner_dead_zone = 40
+/// push_distance = 30
+///
+/// [clipboard]
+/// max_size = 1048576
+///
+/// [files]
+/// inbox = "/home/me/Downloads/okbm"
+///
+/// [discovery]
+/// announce = true
+/// group = "239.255.42.99:4243"
+///
+/// [keyboard]
+/// mode = "keysym"
+/// locks = "receiver"
+///
+/// [[peers]]
+/// id = "192.168.1.34"
+/// position = "right"
+/// output = "DP-1"
+/// segment = [0, 50]
+/// block = { buttons = true, fullscreen = true }
+/// clipboard = false
+/// primary_selection = true
+/// files = true
+/// repeat = { rate = 25, delay_ms = 400, follow_sender = true }
+/// remap = [{ from = ["KeyLeftMeta"], to = ["KeyLeftCtrl"] }]
+///
+/// [[remote_hotkeys]]
+/// keys = ["KeyLeftCtrl", "KeyLeftAlt", "Key1"]
+/// peer = "192.168.1.34"
+///
+/// [[remote_hotkeys]]
+/// keys = ["KeyLeftCtrl", "KeyLeftAlt", "Key0"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    pub id: String,
+    #[serde(default)]
+    pub listen: Vec<String>,
+    #[serde(default)]
+    pub connect: Vec<String>,
+    #[serde(default)]
+    pub motion: Motion,
+    #[serde(default)]
+    pub keyboard: Keyboard,
+    // when reaching an edge switches to the peer behind it
+    #[serde(default)]
+    pub switching: SwitchPolicy,
+    #[serde(default)]
+    pub clipboard: ClipboardSharing,
+    #[serde(default)]
+    pub files: FileSharing,
+    #[serde(default)]
+    pub discovery: Discovery,
+    #[serde(default)]
+    pub peers: Vec<PeerConfig>,
+    #[serde(default, alias = "hotkeys")]
+    pub remote_hotkeys: Vec<RemoteHotkey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerConfig {
+    pub id: String,
+    pub position: Position,
+    // output whose edge leads to the peer, the outermost ones on that side by default
+    #[serde(default)]
+    pub output: Option<String>,
+    // part of the edge leading to the peer, in percent from its top or left end
+    #[serde(default = "whole_edge")]
+    pub segment: (u8, u8),
+    // what keeps the edge from switching to the peer
+    #[serde(default)]
+    pub block: SwitchBlock,
+    // share our clipboard with the peer and take its own
+    #[serde(default = "enabled")]
+    pub clipboard: bool,
+    // same for the primary selection, between Linux peers
+    #[serde(default = "enabled")]
+    pub primary_selection: bool,
+    // send the files dropped on the edge to the peer and take the ones it sends
+    #[serde(default = "enabled")]
+    pub files: bool,
+    #[serde(default)]
+    pub repeat: Repeat,
+    // applied to the keys we send to this peer, see `remap::Rule`
+    #[serde(default)]
+    pub remap: Vec<Rule>,
+}
+
+fn whole_edge() -> (u8, u8) {
+    (0, 100)
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl PeerConfig {
+    /// The screen edge leading to the peer.
+    pub fn edge(&self) -> Edge {
+        Edge {
+            position: self.position,
+            output: self.output.clone(),
+            start: self.segment.0,
+            end: self.segment.1,
+        }
+    }
+}
+
+/// Key repeat applied to the keys a peer sends us. Only on macOS, on Wayland
+/// applications repeat keys at the rate set in the compositor and this is ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Repeat {
+    // keys per second, 0 disables repeat
+    pub rate: u32,
+    pub delay_ms: u32,
+    // use the repeat of the peer's keyboard when it sends it
+    pub follow_sender: bool,
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        let repeat = RepeatInfo::default();
+        Self {
+            rate: repeat.rate,
+            delay_ms: repeat.delay,
+            follow_sender: false,
+        }
+    }
+}
+
+impl Repeat {
+    /// The repeat to apply given the one the sender advertised, if any.
+    pub fn resolve(&self, sender: Option<RepeatInfo>) -> RepeatInfo {
+        match sender {
+            Some(repeat) if self.follow_sender => repeat,
+            _ => RepeatInfo {
+                rate: self.rate,
+                delay: self.delay_ms,
+            },
+        }
+    }
+}
+
+/// Chord switching our input from the peer that has it straight to `peer`,
+/// or back to us without a peer. Chords are only seen while a peer has our input:
+/// grabbing input from the local machine with a chord isn't supported, pressed
+/// locally they go to the focused application as a Wayland client can't watch keys
+/// sent to others. Bind `okbmctl switch <peer>` to a shortcut of the desktop instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteHotkey {
+    pub keys: Vec<Linux>,
+    #[serde(default)]
+    pub peer: Option<String>,
+}
+
+impl RemoteHotkey {
+    /// Whether pressing `key` completes the chord, `pressed` including it.
+    pub fn matches(&self, pressed: &HashSet<Linux>, key: Linux) -> bool {
+        self.keys.contains(&key)
+            && self.keys.len() == pressed.len()
+            && self.keys.iter().all(|k| pressed.contains(k))
+    }
+}
+
+/// Clipboard sharing, see `okbm_clipboard::Clipboard`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardSharing {
+    // bytes of clipboard data sent or accepted at once, over all MIME types
+    pub max_size: usize,
+}
+
+impl Default for ClipboardSharing {
+    fn default() -> Self {
+        Self { max_size: 16 << 20 }
+    }
+}
+
+/// File transfer, files dropped on the edge of a peer are saved in its inbox
+/// and the user is notified.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSharing {
+    // where received files go, `~/Downloads/okbm` by default
+    pub inbox: Option<PathBuf>,
+    // bytes of a single file sent or accepted
+    pub max_size: u64,
+}
+
+impl Default for FileSharing {
+    fn default() -> Self {
+        Self {
+            inbox: None,
+            max_size: 64 << 20,
+        }
+    }
+}
+
+impl FileSharing {
+    pub fn inbox(&self) -> PathBuf {
+        self.inbox.clone().unwrap_or_else(|| {
+            env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join("Downloads")
+                .join("okbm")
+        })
+    }
+}
+
+/// Beacons announcing the daemon on the local network, listed by `okbm discover`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Discovery {
+    // off unless enabled, beacons tell anyone on the network about us and our screens
+    pub announce: bool,
+    // multicast group and port the beacons are sent to
+    pub group: SocketAddrV4,
+    pub interval_ms: u64,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self {
+            announce: false,
+            group: SocketAddrV4::new(Ipv4Addr::new(239, 255, 42, 99), 4243),
+            interval_ms: 2000,
+        }
+    }
+}
+
+/// Pointer motion coalescing, see `coalesce::MotionCoalescer`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Motion {
+    // window during which motion events are summed before being sent, 0 disables it
+    pub coalesce_window_ms: u64,
+    // merge motion events instead of discarding them when the compositor can't keep up
+    pub coalesce_on_backpressure: bool,
+}
+
+impl Default for Motion {
+    fn default() -> Self {
+        Self {
+            coalesce_window_ms: 0,
+            coalesce_on_backpressure: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keyboard {
+    // forward keys by position or by the character they produce with our layout
+    pub mode: KeyMode,
+    // whose Caps/Num/Scroll Lock state applies when a peer takes control of us
+    pub locks: LockSync,
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        if let Some(path) = env::var_os("OKBM_CONFIG") {
+            return path.into();
+        }
+
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_default()
+            .join("okbm")
+            .join("config.toml")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Report::msg(format!("failed to read {}: {e}", path.display())))?;
+
+        let config: Self = toml::from_str(&content)?;
+        for peer in &config.peers {
+            let (start, end) = peer.segment;
+            if start >= end || end > 100 {
+                return Err(Report::msg(format!(
+                    "invalid segment [{start}, {end}] for peer {}",
+                    peer.id
+                )));
+            }
+            // keys held elsewhere aren't sent to a Wayland client
+            #[cfg(all(unix, not(target_os = "macos")))]
+            if !peer.block.keys.is_empty() {
+                return Err(Report::msg(format!(
+                    "held keys can't block switching on wayland, remove block.keys of peer {}",
+                    peer.id
+                )));
+            }
+        }
+        Ok(config)
+    }
+}
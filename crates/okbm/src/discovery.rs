@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{net::UdpSocket, time::Instant};
+
+use crate::{Result, Screen};
+
+// prefix of every beacon, anything else sent to the group is ignored
+const MAGIC: &[u8] = b"okbm-beacon-1";
+
+/// What a daemon announces about itself on the local network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Beacon {
+    pub id: String,
+    // zenoh id of the daemon's session, telling apart daemons with the same id
+    pub fingerprint: String,
+    pub listen: Vec<String>,
+    pub screens: Vec<Screen>,
+}
+
+impl Display for Beacon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.id, self.fingerprint)?;
+        write!(f, "\n  listen: {}", self.listen.join(", "))?;
+        for screen in &self.screens {
+            write!(
+                f,
+                "\n  screen: {} {}x{} @pos ({}, {})",
+                screen.name.as_deref().unwrap_or("display"),
+                screen.width,
+                screen.height,
+                screen.x,
+                screen.y
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Beacon {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(bincode::serialize(self)?);
+        Ok(bytes)
+    }
+
+    /// The beacon in `bytes`, unless they are something else sent to the group.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        bincode::deserialize(bytes.strip_prefix(MAGIC)?).ok()
+    }
+}
+
+/// Sends beacons to the multicast `group`, looped back to the instances on this host.
+pub struct Announcer {
+    socket: UdpSocket,
+    group: SocketAddrV4,
+}
+
+impl Announcer {
+    pub async fn new(group: SocketAddrV4) -> Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        socket.set_multicast_loop_v4(true)?;
+        Ok(Self { socket, group })
+    }
+
+    pub async fn announce(&self, beacon: &Beacon) -> Result<()> {
+        self.socket.send_to(&beacon.encode()?, self.group).await?;
+        Ok(())
+    }
+}
+
+/// Listens to the beacons sent to `group` for `duration` and returns the last
+/// one of each daemon along with where it came from.
+pub async fn discover(
+    group: SocketAddrV4,
+    duration: Duration,
+) -> Result<Vec<(SocketAddr, Beacon)>> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // several listeners on this host share the port
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, group.port()).into())?;
+    socket.join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)?;
+    let socket = UdpSocket::from_std(socket.into())?;
+
+    let mut found = HashMap::new();
+    let deadline = Instant::now() + duration;
+    let mut buf = vec![0; 65536];
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, from) = received?;
+        if let Some(beacon) = Beacon::decode(&buf[..len]) {
+            found.insert(beacon.fingerprint.clone(), (from, beacon));
+        }
+    }
+
+    let mut found: Vec<_> = found.into_values().collect();
+    found.sort_by(|(_, a), (_, b)| a.id.cmp(&b.id));
+    Ok(found)
+}
@@ -0,0 +1,95 @@
+use std::{env, io::ErrorKind, path::PathBuf};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{mpsc, oneshot},
+};
+
+use crate::{Report, Result};
+
+/// A command received on the local socket, answered through `reply`.
+pub struct Request {
+    pub command: String,
+    pub reply: oneshot::Sender<String>,
+}
+
+pub fn socket_path() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join("okbm.sock")
+}
+
+/// Listens on the local socket and forwards every command line to the returned channel.
+pub fn listen() -> Result<mpsc::Receiver<Request>> {
+    let path = socket_path();
+
+    match std::os::unix::net::UnixStream::connect(&path) {
+        Ok(_) => {
+            return Err(Report::msg(format!(
+                "a daemon is already listening on {}",
+                path.display()
+            )));
+        }
+        // a previous instance left its socket behind
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => std::fs::remove_file(&path)?,
+        Err(_) => {}
+    }
+    let listener = UnixListener::bind(&path)?;
+
+    let (tx, rx) = mpsc::channel(8);
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("failed to accept connection: {e}");
+                    continue;
+                }
+            };
+
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = serve(stream, tx).await {
+                    eprintln!("failed to serve request: {e}");
+                }
+            });
+        }
+    });
+
+    Ok(rx)
+}
+
+async fn serve(stream: UnixStream, tx: mpsc::Sender<Request>) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+
+    let mut command = String::new();
+    BufReader::new(reader).read_line(&mut command).await?;
+
+    let (reply, response) = oneshot::channel();
+    tx.send(Request {
+        command: command.trim().to_owned(),
+        reply,
+    })
+    .await
+    .map_err(|_| Report::msg("daemon is shutting down"))?;
+
+    writer.write_all(response.await?.as_bytes()).await?;
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+/// Sends `command` to the running daemon and returns its answer.
+pub async fn request(command: &str) -> Result<String> {
+    let mut stream = UnixStream::connect(socket_path()).await?;
+
+    stream.write_all(format!("{command}\n").as_bytes()).await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+
+    Ok(response)
+}
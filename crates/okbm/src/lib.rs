@@ -3,4 +3,14 @@ pub use eyre::{Report, Result};
 pub use okbm_common::*;
 
 pub use okbm_capture::*;
+pub use okbm_clipboard::*;
 pub use okbm_emulation::*;
+
+pub mod coalesce;
+pub mod config;
+pub mod discovery;
+pub mod ipc;
+pub mod protocol;
+pub mod remap;
+pub mod stats;
+pub mod transfer;
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{CaptureEvent, ClipboardContent, Keymap, RepeatInfo, Selection};
+
+/// Input event forwarded to a peer, stamped with the sender's clock at capture time.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ZenohEvent {
+    pub handle: u32,
+    pub seq: u64,
+    pub timestamp: u64,
+    pub event: CaptureEvent,
+}
+
+/// Messages on the event channel, which delivers them in order.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum EventMessage {
+    Event(ZenohEvent),
+    // sent before `CaptureEvent::Begin` when the pointer enters the peer,
+    // and when the peer asks for our keymap
+    Enter(Handshake),
+}
+
+/// Messages exchanged on the control channel, next to the input events.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ControlMessage {
+    // clock offset estimation, all timestamps in microseconds
+    Ping { t0: u64 },
+    Pong { t0: u64, t1: u64 },
+    // the receiver doesn't have the keymap of the hash it was sent
+    KeymapRequest,
+    // the sender's clipboard or primary selection, sent after `Enter`
+    // when it changed since last time
+    Clipboard(Selection, ClipboardContent),
+    // a file dropped on the receiver's edge, for its inbox
+    File { name: String, data: Vec<u8> },
+}
+
+/// State of the capturing side the receiver needs to reproduce its input.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Handshake {
+    // hash of the sender's keymap, the keymap itself is left out when
+    // the receiver was sent it already
+    pub keymap_hash: Option<u64>,
+    pub keymap: Option<Keymap>,
+    // xkb mask of the sender's Caps/Num/Scroll Lock, when already known,
+    // otherwise they come with its first modifiers event
+    pub locks: Option<u32>,
+    pub repeat: Option<RepeatInfo>,
+}
+
+/// Identifies a keymap across handshakes, only ever compared to hashes from the same sender.
+pub fn keymap_hash(keymap: &Keymap) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    keymap.hash(&mut hasher);
+    hasher.finish()
+}
+
+// input events sent by `from` to `to`
+pub fn event_key(to: &str, from: &str) -> String {
+    format!("okbm/{to}/{from}")
+}
+
+// control messages sent by `from` to `to`
+pub fn control_key(to: &str, from: &str) -> String {
+    format!("okbm/{to}/{from}/control")
+}
+
+// extract the sender id from a key built by `event_key` or `control_key`
+pub fn sender(key: &str) -> Option<&str> {
+    key.strip_prefix("okbm/")?.split('/').nth(1)
+}
+
+// microseconds since the unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
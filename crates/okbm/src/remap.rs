@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{CaptureEvent, Event, KeyboardEvent, ModifierKeys, XMods, scancode::Linux};
+
+/// Replaces the keys of `from` with those of `to` for a peer, configured as
+///
+/// ```toml
+/// remap = [
+///     { from = ["KeyLeftMeta"], to = ["KeyLeftCtrl"] },
+///     { from = ["KeyLeftAlt", "KeyBackspace"], to = ["KeyLeftCtrl", "KeyW"] },
+///     { from = ["KeyCapsLock"], to = [] },
+/// ]
+/// ```
+///
+/// A single key is remapped whenever it's pressed. A chord applies when its last
+/// key is pressed while the others are held, they are released first. An empty
+/// `to` drops the key or chord.
+///
+/// Rules match scancodes, in keysym mode the keys producing a character are sent
+/// as keysyms and aren't remapped, modifiers and other keys still are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub from: Vec<Linux>,
+    #[serde(default)]
+    pub to: Vec<Linux>,
+}
+
+#[derive(Debug, Default)]
+pub struct Remapper {
+    rules: Vec<Rule>,
+    // keys held on our side and the keys sent in their place
+    pressed: HashMap<Linux, Vec<Linux>>,
+}
+
+impl Remapper {
+    pub fn new(rules: &[Rule]) -> Self {
+        let mut rules: Vec<Rule> = rules
+            .iter()
+            .filter(|rule| !rule.from.is_empty())
+            .cloned()
+            .collect();
+        // the longest chord wins
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.from.len()));
+        Self {
+            rules,
+            pressed: HashMap::new(),
+        }
+    }
+
+    /// Forgets the held keys, when the pointer enters the peer again.
+    pub fn reset(&mut self) {
+        self.pressed.clear();
+    }
+
+    /// The events to send in place of `event`.
+    pub fn apply(&mut self, event: CaptureEvent) -> Vec<CaptureEvent> {
+        if self.rules.is_empty() {
+            return vec![event];
+        }
+        match event {
+            CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key { time, key, state })) => {
+                let Ok(key) = Linux::try_from(key) else {
+                    return vec![event];
+                };
+                let keys = match state {
+                    1 => self.press(key),
+                    _ => self.release(key),
+                };
+                keys.into_iter()
+                    .map(|(key, state)| {
+                        CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key {
+                            time,
+                            key: key as u32,
+                            state,
+                        }))
+                    })
+                    .collect()
+            }
+            CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Modifiers {
+                depressed,
+                latched,
+                locked,
+                group,
+            })) => {
+                // the modifiers of the keys we hold are replaced by those of the keys sent
+                let held = self
+                    .pressed
+                    .keys()
+                    .fold(ModifierKeys::empty(), |mods, &key| {
+                        mods | ModifierKeys::from_key(key)
+                    });
+                let sent = self
+                    .pressed
+                    .values()
+                    .flatten()
+                    .fold(ModifierKeys::empty(), |mods, &key| {
+                        mods | ModifierKeys::from_key(key)
+                    });
+                let depressed =
+                    (XMods::from_bits_retain(depressed) - held.to_xmods()) | sent.to_xmods();
+                vec![CaptureEvent::Input(Event::Keyboard(
+                    KeyboardEvent::Modifiers {
+                        depressed: depressed.bits(),
+                        latched,
+                        locked,
+                        group,
+                    },
+                ))]
+            }
+            event => vec![event],
+        }
+    }
+
+    fn press(&mut self, key: Linux) -> Vec<(Linux, u8)> {
+        let mut events = vec![];
+
+        let rule = self.rules.iter().find(|rule| match rule.from.split_last() {
+            Some((last, held)) => *last == key && held.iter().all(|k| self.pressed.contains_key(k)),
+            None => false,
+        });
+        let to = match rule {
+            Some(rule) => {
+                // the rest of the chord is released and stays so until pressed again
+                for held in &rule.from[..rule.from.len() - 1] {
+                    if let Some(sent) = self.pressed.get_mut(held) {
+                        events.extend(sent.drain(..).rev().map(|k| (k, 0)));
+                    }
+                }
+                rule.to.clone()
+            }
+            None => vec![key],
+        };
+
+        events.extend(to.iter().map(|&k| (k, 1)));
+        self.pressed.insert(key, to);
+        events
+    }
+
+    fn release(&mut self, key: Linux) -> Vec<(Linux, u8)> {
+        // pressed before we took over, released as is
+        let sent = self.pressed.remove(&key).unwrap_or_else(|| vec![key]);
+        sent.into_iter().rev().map(|k| (k, 0)).collect()
+    }
+}
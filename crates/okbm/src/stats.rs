@@ -0,0 +1,162 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+// number of clock samples the offset estimate is taken from
+const CLOCK_SAMPLES: usize = 8;
+
+// number of latency samples percentiles are computed on
+const LATENCY_SAMPLES: usize = 1024;
+
+/// NTP-like estimate of the offset between a peer's clock and ours.
+#[derive(Debug, Default)]
+pub struct ClockSync {
+    // (offset, round trip time) in microseconds
+    samples: VecDeque<(i64, u64)>,
+}
+
+impl ClockSync {
+    /// `t0` and `t2` are our clock when the ping was sent and the pong received,
+    /// `t1` is the peer's clock when it answered.
+    pub fn update(&mut self, t0: u64, t1: u64, t2: u64) {
+        let rtt = t2.saturating_sub(t0);
+        let offset = t1 as i64 - (t0 + rtt / 2) as i64;
+
+        if self.samples.len() == CLOCK_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((offset, rtt));
+    }
+
+    // the sample with the smallest round trip is the least affected by queuing delays
+    fn best(&self) -> Option<(i64, u64)> {
+        self.samples.iter().min_by_key(|(_, rtt)| *rtt).copied()
+    }
+
+    /// Peer clock minus local clock, in microseconds.
+    pub fn offset(&self) -> Option<i64> {
+        self.best().map(|(offset, _)| offset)
+    }
+
+    pub fn rtt(&self) -> Option<u64> {
+        self.best().map(|(_, rtt)| rtt)
+    }
+}
+
+/// Statistics about the events received from a peer.
+#[derive(Debug, Default)]
+pub struct PeerStats {
+    latencies: VecDeque<u64>,
+    arrivals: VecDeque<Instant>,
+    last_seq: Option<u64>,
+    received: u64,
+    lost: u64,
+}
+
+impl PeerStats {
+    /// Records an event with sequence number `seq`, `latency` is the time in
+    /// microseconds between its capture and its emulation, if known.
+    pub fn record(&mut self, seq: u64, latency: Option<u64>) {
+        let now = Instant::now();
+
+        self.received += 1;
+        if let Some(last) = self.last_seq
+            && seq > last
+        {
+            self.lost += seq - last - 1;
+        }
+        self.last_seq = Some(seq);
+
+        self.arrivals.push_back(now);
+        while let Some(&t) = self.arrivals.front() {
+            if now.duration_since(t) > Duration::from_secs(1) {
+                self.arrivals.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if let Some(latency) = latency {
+            if self.latencies.len() == LATENCY_SAMPLES {
+                self.latencies.pop_front();
+            }
+            self.latencies.push_back(latency);
+        }
+    }
+
+    pub fn report(&self, peer: &str, clock: &ClockSync, dropped: u64) -> PeerReport {
+        let mut latencies: Vec<u64> = self.latencies.iter().copied().collect();
+        latencies.sort_unstable();
+
+        let percentile =
+            |p: usize| (!latencies.is_empty()).then(|| latencies[(latencies.len() - 1) * p / 100]);
+
+        let now = Instant::now();
+        let rate = self
+            .arrivals
+            .iter()
+            .filter(|&&t| now.duration_since(t) <= Duration::from_secs(1))
+            .count();
+
+        PeerReport {
+            peer: peer.to_owned(),
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            rate,
+            received: self.received,
+            lost: self.lost,
+            dropped,
+            offset: clock.offset(),
+            rtt: clock.rtt(),
+        }
+    }
+}
+
+/// Snapshot of the statistics of a peer, as reported by `okbm status`.
+#[derive(Debug, Clone)]
+pub struct PeerReport {
+    pub peer: String,
+    pub p50: Option<u64>,
+    pub p95: Option<u64>,
+    pub p99: Option<u64>,
+    pub rate: usize,
+    pub received: u64,
+    pub lost: u64,
+    pub dropped: u64,
+    pub offset: Option<i64>,
+    pub rtt: Option<u64>,
+}
+
+// formats microseconds as milliseconds
+struct Millis(Option<f64>);
+
+impl Display for Millis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(us) => write!(f, "{:.2}ms", us / 1000.),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+impl Display for PeerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: latency p50 {} p95 {} p99 {}, {} events/s, {} received, {} lost, {} dropped, clock offset {} (rtt {})",
+            self.peer,
+            Millis(self.p50.map(|v| v as f64)),
+            Millis(self.p95.map(|v| v as f64)),
+            Millis(self.p99.map(|v| v as f64)),
+            self.rate,
+            self.received,
+            self.lost,
+            self.dropped,
+            Millis(self.offset.map(|v| v as f64)),
+            Millis(self.rtt.map(|v| v as f64)),
+        )
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use tokio::process::Command;
+
+use crate::{Report, Result};
+
+/// Name and content of a file dropped on the edge of a peer, to be sent to it.
+pub async fn read(path: &Path, max_size: u64) -> Result<(String, Vec<u8>)> {
+    let metadata = tokio::fs::metadata(path).await?;
+    if !metadata.is_file() {
+        return Err(Report::msg(format!("{} is not a file", path.display())));
+    }
+    if metadata.len() > max_size {
+        return Err(Report::msg(format!(
+            "{} is over the size limit",
+            path.display()
+        )));
+    }
+    let Some(name) = path.file_name() else {
+        return Err(Report::msg(format!("{} has no name", path.display())));
+    };
+    Ok((
+        name.to_string_lossy().into_owned(),
+        tokio::fs::read(path).await?,
+    ))
+}
+
+/// Saves a file received from a peer in `inbox`, next to the files of the same
+/// name as `name (1).ext` and so on, and returns where it went.
+pub fn save(inbox: &Path, name: &str, data: &[u8]) -> Result<PathBuf> {
+    // the peer only gets to choose the name, not the directory
+    let Some(name) = Path::new(name).file_name().map(Path::new) else {
+        return Err(Report::msg(format!("invalid file name {name:?}")));
+    };
+    fs::create_dir_all(inbox)?;
+
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    for n in 0.. {
+        let path = match n {
+            0 => inbox.join(name),
+            n => inbox.join(format!("{stem} ({n}){extension}")),
+        };
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(data)?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!()
+}
+
+/// Shows a desktop notification, with notify-send on Linux.
+pub fn notify(summary: &str, body: &str) {
+    #[cfg(target_os = "macos")]
+    let command = Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification {body:?} with title {summary:?}"
+        ))
+        .spawn();
+
+    #[cfg(not(target_os = "macos"))]
+    let command = Command::new("notify-send")
+        .args(["--app-name", "okbm", summary, body])
+        .spawn();
+
+    if let Err(e) = command {
+        eprintln!("failed to notify: {e}");
+    }
+}
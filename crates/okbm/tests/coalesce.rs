@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use okbm::{CaptureEvent, Event, PointerEvent, coalesce::MotionCoalescer};
+
+fn motion(time: u32, dx: f64, dy: f64) -> CaptureEvent {
+    CaptureEvent::Input(Event::Pointer(PointerEvent::Motion { time, dx, dy }))
+}
+
+fn frame() -> CaptureEvent {
+    CaptureEvent::Input(Event::Pointer(PointerEvent::Frame))
+}
+
+fn button(state: u32) -> CaptureEvent {
+    CaptureEvent::Input(Event::Pointer(PointerEvent::Button {
+        time: 0,
+        button: 0x110,
+        state,
+    }))
+}
+
+fn coalescer() -> MotionCoalescer {
+    MotionCoalescer::new(Duration::from_millis(4))
+}
+
+#[test]
+fn zero_window_passes_events_through() {
+    let mut coalescer = MotionCoalescer::new(Duration::ZERO);
+    assert_eq!(
+        coalescer.push(1, motion(1, 1., 2.)),
+        [(1, motion(1, 1., 2.))]
+    );
+    assert_eq!(coalescer.push(2, frame()), [(2, frame())]);
+    assert_eq!(coalescer.deadline(), None);
+}
+
+#[test]
+fn motion_frames_are_summed() {
+    let mut coalescer = coalescer();
+    let before = Instant::now();
+    for (timestamp, event) in [
+        (10, motion(1, 1., 2.)),
+        (11, frame()),
+        (20, motion(2, 3., -4.)),
+        (21, frame()),
+    ] {
+        assert!(coalescer.push(timestamp, event).is_empty());
+    }
+    assert!(coalescer.deadline().is_some_and(|d| d >= before));
+
+    // stamped with the capture time of the first motion, timed with the last
+    assert_eq!(coalescer.flush(), [(10, motion(2, 4., -2.)), (10, frame())]);
+    assert_eq!(coalescer.deadline(), None);
+    assert!(coalescer.flush().is_empty());
+}
+
+#[test]
+fn other_frames_flush_the_motion_first() {
+    let mut coalescer = coalescer();
+    assert!(coalescer.push(10, motion(1, 1., 1.)).is_empty());
+    assert!(coalescer.push(11, frame()).is_empty());
+    assert!(coalescer.push(20, button(1)).is_empty());
+    assert_eq!(
+        coalescer.push(21, frame()),
+        [
+            (10, motion(1, 1., 1.)),
+            (10, frame()),
+            (20, button(1)),
+            (21, frame())
+        ]
+    );
+    assert_eq!(coalescer.deadline(), None);
+}
+
+#[test]
+fn frames_with_buttons_are_kept_whole() {
+    let mut coalescer = coalescer();
+    assert!(coalescer.push(10, motion(1, 2., 0.)).is_empty());
+    assert!(coalescer.push(10, button(1)).is_empty());
+    assert_eq!(
+        coalescer.push(10, frame()),
+        [(10, motion(1, 2., 0.)), (10, button(1)), (10, frame())]
+    );
+    assert_eq!(coalescer.deadline(), None);
+}
+
+#[test]
+fn flushing_keeps_the_frame_being_received() {
+    let mut coalescer = coalescer();
+    assert!(coalescer.push(10, motion(1, 1., 0.)).is_empty());
+    assert!(coalescer.push(11, frame()).is_empty());
+    assert!(coalescer.push(20, motion(2, 0., 1.)).is_empty());
+    assert!(coalescer.push(20, button(0)).is_empty());
+    assert_eq!(coalescer.flush(), [(10, motion(1, 1., 0.)), (10, frame())]);
+    assert_eq!(
+        coalescer.push(21, frame()),
+        [(20, motion(2, 0., 1.)), (20, button(0)), (21, frame())]
+    );
+}
+
+#[test]
+fn keys_pass_without_pending_motion() {
+    let mut coalescer = coalescer();
+    let key = CaptureEvent::Input(Event::Keyboard(okbm::KeyboardEvent::Key {
+        time: 0,
+        key: 30,
+        state: 1,
+    }));
+    assert_eq!(coalescer.push(5, key.clone()), [(5, key)]);
+}
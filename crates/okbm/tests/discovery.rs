@@ -0,0 +1,61 @@
+use std::{
+    net::{Ipv4Addr, SocketAddrV4},
+    time::Duration,
+};
+
+use okbm::{
+    Screen,
+    discovery::{self, Announcer, Beacon},
+};
+
+fn beacon(id: &str, fingerprint: &str) -> Beacon {
+    Beacon {
+        id: id.to_owned(),
+        fingerprint: fingerprint.to_owned(),
+        listen: vec!["tcp/192.168.1.34:7447".to_owned()],
+        screens: vec![Screen {
+            name: Some("DP-1".to_owned()),
+            x: 0,
+            y: 0,
+            width: 2560,
+            height: 1440,
+        }],
+    }
+}
+
+#[test]
+fn beacon_round_trip() {
+    let beacon = beacon("desk", "0123abcd");
+    assert_eq!(Beacon::decode(&beacon.encode().unwrap()), Some(beacon));
+}
+
+#[test]
+fn foreign_datagrams_are_ignored() {
+    let mut bytes = beacon("desk", "0123abcd").encode().unwrap();
+    bytes[0] ^= 0xff;
+    assert_eq!(Beacon::decode(&bytes), None);
+    assert_eq!(Beacon::decode(b"okbm"), None);
+    assert_eq!(Beacon::decode(b""), None);
+}
+
+#[tokio::test]
+async fn discovers_the_daemons_on_this_host() {
+    // a port of its own, so other daemons on the host don't show up
+    let group = SocketAddrV4::new(Ipv4Addr::new(239, 255, 42, 99), 47_531);
+    let announcers = [
+        (Announcer::new(group).await.unwrap(), beacon("desk", "a")),
+        (Announcer::new(group).await.unwrap(), beacon("laptop", "b")),
+    ];
+
+    let discover = tokio::spawn(discovery::discover(group, Duration::from_millis(500)));
+    for _ in 0..5 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        for (announcer, beacon) in &announcers {
+            announcer.announce(beacon).await.unwrap();
+        }
+    }
+
+    let found = discover.await.unwrap().unwrap();
+    let found: Vec<_> = found.into_iter().map(|(_, beacon)| beacon).collect();
+    assert_eq!(found, [beacon("desk", "a"), beacon("laptop", "b")]);
+}
@@ -0,0 +1,75 @@
+use okbm::{HeldKeys, KeyboardEvent, config::RemoteHotkey, scancode::Linux};
+
+fn key(key: Linux, state: u8) -> KeyboardEvent {
+    KeyboardEvent::Key {
+        time: 0,
+        key: key as u32,
+        state,
+    }
+}
+
+fn keysym(key: Linux, keysym: u32, state: u8) -> KeyboardEvent {
+    KeyboardEvent::Keysym {
+        time: 0,
+        key: key as u32,
+        keysym,
+        state,
+    }
+}
+
+fn hotkey() -> RemoteHotkey {
+    RemoteHotkey {
+        keys: vec![Linux::KeyLeftCtrl, Linux::KeyLeftAlt, Linux::Key1],
+        peer: Some("laptop".to_owned()),
+    }
+}
+
+#[test]
+fn chords_complete_with_keysyms() {
+    let mut held = HeldKeys::default();
+    held.update(&key(Linux::KeyLeftCtrl, 1));
+    held.update(&key(Linux::KeyLeftAlt, 1));
+    assert!(!hotkey().matches(held.keys(), Linux::KeyLeftAlt));
+
+    // in keysym mode the digit is sent as the character it types
+    held.update(&keysym(Linux::Key1, 0x31, 1));
+    assert!(hotkey().matches(held.keys(), Linux::Key1));
+
+    held.update(&keysym(Linux::Key1, 0x31, 0));
+    assert_eq!(held.keys().len(), 2);
+}
+
+#[test]
+fn keys_are_released_the_way_they_were_pressed() {
+    let mut held = HeldKeys::default();
+    held.update(&key(Linux::KeyLeftShift, 1));
+    held.update(&keysym(Linux::KeyA, 0x41, 1));
+
+    let mut releases: Vec<_> = held.releases(7).collect();
+    releases.sort_by_key(|&(key, _)| key as u32);
+    assert_eq!(
+        releases,
+        [
+            (
+                Linux::KeyA,
+                KeyboardEvent::Keysym {
+                    time: 7,
+                    key: Linux::KeyA as u32,
+                    keysym: 0x41,
+                    state: 0,
+                }
+            ),
+            (
+                Linux::KeyLeftShift,
+                KeyboardEvent::Key {
+                    time: 7,
+                    key: Linux::KeyLeftShift as u32,
+                    state: 0,
+                }
+            ),
+        ]
+    );
+
+    held.clear();
+    assert_eq!(held.releases(7).count(), 0);
+}
@@ -0,0 +1,141 @@
+use okbm::{
+    CaptureEvent, Event, KeyboardEvent, XMods,
+    remap::{Remapper, Rule},
+    scancode::Linux,
+};
+
+fn key(key: Linux, state: u8) -> CaptureEvent {
+    CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key {
+        time: 0,
+        key: key as u32,
+        state,
+    }))
+}
+
+fn modifiers(depressed: XMods) -> CaptureEvent {
+    CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Modifiers {
+        depressed: depressed.bits(),
+        latched: 0,
+        locked: 0,
+        group: 0,
+    }))
+}
+
+fn remapper() -> Remapper {
+    Remapper::new(&[
+        Rule {
+            from: vec![Linux::KeyLeftMeta],
+            to: vec![Linux::KeyLeftCtrl],
+        },
+        Rule {
+            from: vec![Linux::KeyBackspace],
+            to: vec![Linux::KeyDelete],
+        },
+        Rule {
+            from: vec![Linux::KeyLeftAlt, Linux::KeyBackspace],
+            to: vec![Linux::KeyLeftCtrl, Linux::KeyW],
+        },
+        Rule {
+            from: vec![Linux::KeyCapsLock],
+            to: vec![],
+        },
+    ])
+}
+
+#[test]
+fn single_keys_are_replaced() {
+    let mut remapper = remapper();
+    assert_eq!(
+        remapper.apply(key(Linux::KeyLeftMeta, 1)),
+        [key(Linux::KeyLeftCtrl, 1)]
+    );
+    assert_eq!(
+        remapper.apply(key(Linux::KeyLeftMeta, 0)),
+        [key(Linux::KeyLeftCtrl, 0)]
+    );
+    assert_eq!(remapper.apply(key(Linux::KeyA, 1)), [key(Linux::KeyA, 1)]);
+}
+
+#[test]
+fn chords_release_the_held_keys_first() {
+    let mut remapper = remapper();
+    assert_eq!(
+        remapper.apply(key(Linux::KeyLeftAlt, 1)),
+        [key(Linux::KeyLeftAlt, 1)]
+    );
+    // the longest chord wins over the single backspace rule
+    assert_eq!(
+        remapper.apply(key(Linux::KeyBackspace, 1)),
+        [
+            key(Linux::KeyLeftAlt, 0),
+            key(Linux::KeyLeftCtrl, 1),
+            key(Linux::KeyW, 1)
+        ]
+    );
+    // released in reverse, alt stays released
+    assert_eq!(
+        remapper.apply(key(Linux::KeyBackspace, 0)),
+        [key(Linux::KeyW, 0), key(Linux::KeyLeftCtrl, 0)]
+    );
+    assert!(remapper.apply(key(Linux::KeyLeftAlt, 0)).is_empty());
+}
+
+#[test]
+fn chords_need_the_whole_chord_held() {
+    let mut remapper = remapper();
+    assert_eq!(
+        remapper.apply(key(Linux::KeyBackspace, 1)),
+        [key(Linux::KeyDelete, 1)]
+    );
+    // pressing alt afterwards doesn't change what the held key was sent as
+    remapper.apply(key(Linux::KeyLeftAlt, 1));
+    assert_eq!(
+        remapper.apply(key(Linux::KeyBackspace, 0)),
+        [key(Linux::KeyDelete, 0)]
+    );
+}
+
+#[test]
+fn empty_rules_drop_keys() {
+    let mut remapper = remapper();
+    assert!(remapper.apply(key(Linux::KeyCapsLock, 1)).is_empty());
+    assert!(remapper.apply(key(Linux::KeyCapsLock, 0)).is_empty());
+}
+
+#[test]
+fn keys_pressed_before_are_released_as_is() {
+    let mut remapper = remapper();
+    assert_eq!(
+        remapper.apply(key(Linux::KeyLeftMeta, 0)),
+        [key(Linux::KeyLeftMeta, 0)]
+    );
+
+    remapper.apply(key(Linux::KeyLeftMeta, 1));
+    remapper.reset();
+    assert_eq!(
+        remapper.apply(key(Linux::KeyLeftMeta, 0)),
+        [key(Linux::KeyLeftMeta, 0)]
+    );
+}
+
+#[test]
+fn modifiers_follow_the_keys_sent() {
+    let mut remapper = remapper();
+    remapper.apply(key(Linux::KeyLeftMeta, 1));
+    assert_eq!(
+        remapper.apply(modifiers(XMods::Mod4Mask | XMods::ShiftMask)),
+        [modifiers(XMods::ControlMask | XMods::ShiftMask)]
+    );
+}
+
+#[test]
+fn keysyms_are_not_remapped() {
+    let mut remapper = remapper();
+    let keysym = CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Keysym {
+        time: 0,
+        key: Linux::KeyA as u32,
+        keysym: 0x61,
+        state: 1,
+    }));
+    assert_eq!(remapper.apply(keysym.clone()), [keysym]);
+}
@@ -0,0 +1,85 @@
+use okbm::stats::{ClockSync, PeerStats};
+
+#[test]
+fn clock_offset_of_symmetric_round_trip() {
+    let mut clock = ClockSync::default();
+    // the peer is 500us ahead, each way takes 100us
+    clock.update(1_000, 1_600, 1_200);
+    assert_eq!(clock.offset(), Some(500));
+    assert_eq!(clock.rtt(), Some(200));
+}
+
+#[test]
+fn clock_prefers_the_fastest_round_trip() {
+    let mut clock = ClockSync::default();
+    clock.update(0, 5_000, 4_000);
+    clock.update(10_000, 10_550, 10_100);
+    clock.update(20_000, 22_000, 23_000);
+    assert_eq!(clock.rtt(), Some(100));
+    assert_eq!(clock.offset(), Some(500));
+}
+
+#[test]
+fn clock_forgets_old_samples() {
+    let mut clock = ClockSync::default();
+    clock.update(0, 0, 10);
+    for i in 1..=8 {
+        clock.update(i * 1_000, i * 1_000 + 1_000, i * 1_000 + 500);
+    }
+    assert_eq!(clock.rtt(), Some(500));
+    assert_eq!(clock.offset(), Some(750));
+}
+
+#[test]
+fn no_clock_samples() {
+    let clock = ClockSync::default();
+    assert_eq!(clock.offset(), None);
+    assert_eq!(clock.rtt(), None);
+}
+
+#[test]
+fn latency_percentiles() {
+    let mut stats = PeerStats::default();
+    // shuffled so the order they arrive in doesn't matter
+    for (seq, latency) in (1..=100).rev().enumerate() {
+        stats.record(seq as u64, Some(latency));
+    }
+    let report = stats.report("peer", &ClockSync::default(), 0);
+    assert_eq!(report.p50, Some(50));
+    assert_eq!(report.p95, Some(95));
+    assert_eq!(report.p99, Some(99));
+    assert_eq!(report.received, 100);
+    assert_eq!(report.lost, 0);
+}
+
+#[test]
+fn percentiles_of_a_single_sample() {
+    let mut stats = PeerStats::default();
+    stats.record(0, Some(42));
+    let report = stats.report("peer", &ClockSync::default(), 0);
+    assert_eq!(
+        (report.p50, report.p95, report.p99),
+        (Some(42), Some(42), Some(42))
+    );
+}
+
+#[test]
+fn no_latency_without_clock() {
+    let mut stats = PeerStats::default();
+    stats.record(0, None);
+    let report = stats.report("peer", &ClockSync::default(), 3);
+    assert_eq!(report.p50, None);
+    assert_eq!(report.received, 1);
+    assert_eq!(report.dropped, 3);
+}
+
+#[test]
+fn gaps_in_sequence_numbers_are_lost() {
+    let mut stats = PeerStats::default();
+    for seq in [0, 1, 4, 5, 9] {
+        stats.record(seq, None);
+    }
+    let report = stats.report("peer", &ClockSync::default(), 0);
+    assert_eq!(report.received, 5);
+    assert_eq!(report.lost, 5);
+}
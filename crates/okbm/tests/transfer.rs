@@ -0,0 +1,50 @@
+use std::fs;
+
+use okbm::transfer;
+
+#[test]
+fn saves_in_the_inbox() {
+    let dir = tempfile::tempdir().unwrap();
+    let inbox = dir.path().join("inbox");
+    let path = transfer::save(&inbox, "notes.txt", b"hello").unwrap();
+    assert_eq!(path, inbox.join("notes.txt"));
+    assert_eq!(fs::read(&path).unwrap(), b"hello");
+}
+
+#[test]
+fn names_in_use_are_numbered() {
+    let dir = tempfile::tempdir().unwrap();
+    let inbox = dir.path();
+    let paths: Vec<_> = (0..3)
+        .map(|i| transfer::save(inbox, "photo.png", &[i]).unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            inbox.join("photo.png"),
+            inbox.join("photo (1).png"),
+            inbox.join("photo (2).png")
+        ]
+    );
+    assert_eq!(fs::read(&paths[2]).unwrap(), [2]);
+
+    let path = transfer::save(inbox, "README", b"").unwrap();
+    assert_eq!(path, inbox.join("README"));
+    let path = transfer::save(inbox, "README", b"").unwrap();
+    assert_eq!(path, inbox.join("README (1)"));
+}
+
+#[test]
+fn peers_only_choose_the_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let inbox = dir.path().join("inbox");
+    let path = transfer::save(&inbox, "../../escaped.txt", b"").unwrap();
+    assert_eq!(path, inbox.join("escaped.txt"));
+    let path = transfer::save(&inbox, "/etc/passwd", b"").unwrap();
+    assert_eq!(path, inbox.join("passwd"));
+
+    for name in ["..", "/", ""] {
+        assert!(transfer::save(&inbox, name, b"").is_err(), "{name:?}");
+    }
+    assert!(!dir.path().join("escaped.txt").exists());
+}